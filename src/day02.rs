@@ -55,7 +55,7 @@ enum Token {
   Down(usize),
 }
 
-fn parse_token<'a>(i: &'a str) -> IResult<&'a str, Token> {
+fn parse_token(i: &str) -> IResult<&str, Token> {
   map(
     separated_pair(cut(alpha1), space1, cut(digit1)),
     |(tok, n)| match tok {
@@ -18,15 +18,15 @@ pub fn day(input: String) -> (usize, usize) {
 
 // mult by 2 and compare with full list length
 // to get around rounding issues
-fn get_most_common(full_list_len: usize, v: &Vec<usize>) -> usize {
+fn get_most_common(full_list_len: usize, v: &[usize]) -> usize {
   (full_list_len <= v.iter().sum::<usize>() * 2) as usize
 }
 
-fn get_least_common(full_list_len: usize, v: &Vec<usize>) -> usize {
+fn get_least_common(full_list_len: usize, v: &[usize]) -> usize {
   (v.iter().sum::<usize>() * 2 < full_list_len) as usize
 }
 
-fn to_n(v: &Vec<usize>) -> usize {
+fn to_n(v: &[usize]) -> usize {
   v.iter().fold(0, |a, b| a * 2 + b)
 }
 
@@ -44,7 +44,7 @@ fn part_1(input: &TParsed) -> usize {
 }
 
 fn part_2(input: &TParsed) -> usize {
-  let filter = |v: &mut TParsed, f: fn(cmp: usize, v: &Vec<usize>) -> usize| {
+  let filter = |v: &mut TParsed, f: fn(cmp: usize, v: &[usize]) -> usize| {
     for p in 0..input[0].len() {
       let bit_criteria = f(v.len(), &transpose(v.to_owned())[p]);
       v.retain(|v| v[p] == bit_criteria);
@@ -78,18 +78,18 @@ fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
     .collect()
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, parse_token))(i)
 }
 
-fn parse_token<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_token(i: &str) -> IResult<&str, TParsedSub> {
   many1(map_res(take(1usize), |s: &str| s.parse()))(i)
 }
 
@@ -19,7 +19,7 @@ fn prep_input(input: &TParsed) -> Vec<(usize, usize)> {
   i.iter().map(|b| final_score(ns, b)).collect()
 }
 
-fn part_1(input: &Vec<(usize, usize)>) -> usize {
+fn part_1(input: &[(usize, usize)]) -> usize {
   input
     .iter()
     .min_by(|(x1, _), (x2, _)| x1.cmp(x2))
@@ -27,7 +27,7 @@ fn part_1(input: &Vec<(usize, usize)>) -> usize {
     .1
 }
 
-fn part_2(input: &Vec<(usize, usize)>) -> usize {
+fn part_2(input: &[(usize, usize)]) -> usize {
   input
     .iter()
     .max_by(|(x1, _), (x2, _)| x1.cmp(x2))
@@ -35,7 +35,7 @@ fn part_2(input: &Vec<(usize, usize)>) -> usize {
     .1
 }
 
-fn final_score(xs: &Vec<usize>, board: &TParsedSub) -> (usize, usize) {
+fn final_score(xs: &[usize], board: &TParsedSub) -> (usize, usize) {
   let tpsd = transpose(board.to_owned());
   let mut n = 0;
 
@@ -73,21 +73,21 @@ fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
     .collect()
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn get_dig<'a, A>(i: &'a str) -> IResult<&'a str, A>
+fn get_dig<A>(i: &str) -> IResult<&str, A>
 where
   A: std::str::FromStr,
 {
   map_res(digit1, |x: &str| x.parse())(i)
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   let a = separated_list1(complete::char(','), get_dig);
   let b = preceded(
     pair(pair(newline, newline), many0(complete::char(' '))),
@@ -99,7 +99,7 @@ fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
   all_consuming(pair(a, b))(i)
 }
 
-fn parse_token<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_token(i: &str) -> IResult<&str, TParsedSub> {
   separated_list1(
     pair(newline, many0(complete::char(' '))),
     separated_list1(many1(complete::char(' ')), get_dig),
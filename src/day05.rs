@@ -15,10 +15,10 @@ pub fn day(input: String) -> (usize, usize) {
   (p1, p2)
 }
 
-fn count_overlapping<'a>(input: &'a TParsed) -> usize {
+fn count_overlapping(input: &TParsed) -> usize {
   let mut m = vec![vec![0; 1000]; 1000];
   for (from, to) in input {
-    let dir = (to - from).map(|x| signum(x));
+    let dir = (to - from).map(signum);
     let mut s = from.to_owned();
     m[s.y as usize][s.x as usize] += 1;
     while s != *to {
@@ -40,18 +40,18 @@ fn part_2(input: &TParsed) -> usize {
   count_overlapping(input)
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, parse_token))(i)
 }
 
-fn parse_token<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_token(i: &str) -> IResult<&str, TParsedSub> {
   map(
     separated_list1(
       tag(" -> "),
@@ -46,18 +46,18 @@ fn get_n_fishies(input: &TParsed, n_days: usize) -> usize {
   fishies.iter().sum()
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(char(','), get_dig))(i)
 }
 
-fn get_dig<'a, A>(i: &'a str) -> IResult<&'a str, A>
+fn get_dig<A>(i: &str) -> IResult<&str, A>
 where
   A: std::str::FromStr,
 {
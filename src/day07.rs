@@ -27,7 +27,7 @@ fn part_1(input: &TParsed) -> usize {
 
   input
     .iter()
-    .map(|x| ((*x as isize) - med).abs() as usize)
+    .map(|x| ((*x as isize) - med).unsigned_abs())
     .sum()
 }
 
@@ -37,7 +37,7 @@ fn part_2(input: &TParsed) -> usize {
   let get_res = |m: isize| {
     input
       .iter()
-      .map(|x| (0..=((*x as isize) - m).abs() as usize).sum::<usize>())
+      .map(|x| (0..=((*x as isize) - m).unsigned_abs()).sum::<usize>())
       .sum()
   };
 
@@ -47,18 +47,18 @@ fn part_2(input: &TParsed) -> usize {
   )
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(char(','), get_dig))(i)
 }
 
-fn get_dig<'a, A>(i: &'a str) -> IResult<&'a str, A>
+fn get_dig<A>(i: &str) -> IResult<&str, A>
 where
   A: std::str::FromStr,
 {
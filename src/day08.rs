@@ -19,9 +19,8 @@ fn part_1(input: &TParsed) -> usize {
   let dig_lens = [2, 3, 4, 7];
   input
     .iter()
-    .map(|(_, o)| o)
-    .flatten()
-    .filter(|s| dig_lens.iter().any(|x| *x == s.len()))
+    .flat_map(|(_, o)| o)
+    .filter(|s| dig_lens.contains(&s.len()))
     .count()
 }
 
@@ -74,22 +73,22 @@ fn part_2(input: &TParsed) -> usize {
   r.iter().sum()
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, parse_line))(i)
 }
 
-fn parse_line<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_line(i: &str) -> IResult<&str, TParsedSub> {
   separated_pair(parse_sq, tag(" | "), parse_sq)(i)
 }
 
-fn parse_sq<'a>(i: &'a str) -> IResult<&'a str, TParsedLn> {
+fn parse_sq(i: &str) -> IResult<&str, TParsedLn> {
   separated_list1(char(' '), map(alpha1, |s: &str| s.to_string()))(i)
 }
 
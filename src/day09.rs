@@ -71,7 +71,7 @@ fn capture_nbs(
   let g = |(y, x): (usize, usize)| input[y][x];
 
   let nbs = get_nbs((sy, sx), input.len(), input[0].len())
-    .difference(&seen)
+    .difference(seen)
     .filter_map(|p| {
       if g((sy, sx)) <= g(*p) && g(*p) != 9 {
         Some(*p)
@@ -90,18 +90,18 @@ fn capture_nbs(
     })
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, parse_line))(i)
 }
 
-fn parse_line<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_line(i: &str) -> IResult<&str, TParsedSub> {
   many1(map_res(take(1u8), |s: &str| s.parse()))(i)
 }
 
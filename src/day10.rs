@@ -53,18 +53,18 @@ fn part_2(input: &TParsed) -> usize {
   res[res.len() / 2]
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => remove_pairs(&v),
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, parse_line))(i)
 }
 
-fn parse_line<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_line(i: &str) -> IResult<&str, TParsedSub> {
   map(many1(satisfy(|c| c.is_ascii_punctuation())), |cs| {
     cs.iter().collect()
   })(i)
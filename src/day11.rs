@@ -1,146 +1,200 @@
-use itertools::Itertools;
-use nom::bytes::complete::take;
-use nom::character::complete::newline;
-use nom::multi::many1;
-use nom::{combinator::*, multi::separated_list1, IResult};
-
-type TParsed = Vec<TParsedSub>;
-type TParsedSub = Vec<u8>;
-
-pub fn day(input: String) -> (usize, usize) {
-  let parsed_input = parse(&input);
-  let p1 = part_1(&parsed_input);
-  let p2 = part_2(&parsed_input);
-  (p1, p2)
-}
-
-fn part_1(input: &TParsed) -> usize {
-  let mut map = input.to_owned();
-  let mut flash_cnt = 0;
-
-  for _ in 0..100 {
-    step_1(&mut map);
-    loop {
-      match step_2(&mut map) {
-        0 => break,
-        n => flash_cnt += n,
-      }
-    }
-  }
-
-  flash_cnt
-}
-
-fn part_2(input: &TParsed) -> usize {
-  let mut map = input.to_owned();
-  let mut steps = 0;
-
-  loop {
-    steps += 1;
-    let mut flash_cnt = 0;
-    step_1(&mut map);
-    loop {
-      match step_2(&mut map) {
-        0 => break,
-        n => flash_cnt += n,
-      }
-    }
-
-    if flash_cnt == 100 {
-      break;
-    }
-  }
-
-  steps
-}
-
-fn get_adjacent((y, x): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
-  let satisfy = |n| n >= 0 && n < 10;
-  let map_to_adjacent = move |p: (isize, isize)| match p {
-    (0, 0) => None,
-    (m, n) => {
-      let (o, p) = (m + y as isize, n + x as isize);
-      if satisfy(o) && satisfy(p) {
-        Some((o as usize, p as usize))
-      } else {
-        None
-      }
-    }
-  };
-  (-1..2).cartesian_product(-1..2).filter_map(map_to_adjacent)
-}
-
-fn step_1(map: &mut TParsed) {
-  map.iter_mut().flatten().for_each(|i| *i += 1);
-}
-
-fn step_2(map: &mut TParsed) -> usize {
-  let mut flashed = [[0; 10]; 10];
-
-  for (y, ln) in map.iter_mut().enumerate() {
-    for (x, n) in ln.iter_mut().enumerate() {
-      if *n > 9 {
-        *n = 0;
-        flashed[y][x] = 1;
-      }
-    }
-  }
-
-  for (y, ln) in map.iter_mut().enumerate() {
-    for (x, n) in ln.iter_mut().enumerate() {
-      if *n > 0 {
-        for (o, p) in get_adjacent((y, x)) {
-          *n += flashed[o][p];
-        }
-      }
-    }
-  }
-
-  flashed.iter().flatten().sum::<u8>() as usize
-}
-
-fn parse<'a>(input: &'a str) -> TParsed {
-  match try_parse(input) {
-    Ok((_, v)) => v,
-    Err(e) => panic!("error parsing: {:?}", e),
-  }
-}
-
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
-  all_consuming(separated_list1(newline, parse_line))(i)
-}
-
-fn parse_line<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
-  many1(map_res(take(1u8), |s: &str| s.parse()))(i)
-}
-
-#[test]
-fn show_parse_11() {
-  let input = parse(EXAMPLE_INPUT);
-  println!("{:?}", input);
-}
-
-#[test]
-fn test_example_1_11() {
-  let input = parse(EXAMPLE_INPUT);
-  assert_eq!(part_1(&input), 1656)
-}
-
-#[test]
-fn test_example_2_11() {
-  let input = parse(EXAMPLE_INPUT);
-  assert_eq!(part_2(&input), 195)
-}
-
-#[cfg(test)]
-const EXAMPLE_INPUT: &str = "\
-5483143223
-2745854711
-5264556173
-6141336146
-6357385478
-4167524645
-2176841721
-6882881134
-4846848554
-5283751526";
+use itertools::Itertools;
+use nom::bytes::complete::take;
+use nom::character::complete::newline;
+use nom::multi::many1;
+use nom::{combinator::*, multi::separated_list1, IResult};
+
+type TParsed = Vec<TParsedSub>;
+type TParsedSub = Vec<u8>;
+
+pub fn day(input: String) -> (usize, usize) {
+  let parsed_input = parse(&input);
+  let p1 = part_1(&parsed_input);
+  let p2 = part_2(&parsed_input);
+  (p1, p2)
+}
+
+fn part_1(input: &TParsed) -> usize {
+  steps(input).take(100).map(|s| s.count).sum()
+}
+
+fn part_2(input: &TParsed) -> usize {
+  let size: usize = input.iter().map(|ln| ln.len()).sum();
+  steps(input).position(|s| s.count == size).unwrap() + 1
+}
+
+#[derive(Debug, PartialEq)]
+struct Step {
+  count: usize,
+  cells: Vec<(usize, usize)>,
+}
+
+// an endless run of steps; cells are (y, x) in the order they flashed
+fn steps(input: &TParsed) -> impl Iterator<Item = Step> {
+  let mut map = input.to_owned();
+  std::iter::repeat_with(move || {
+    let mut cells = Vec::new();
+    step_1(&mut map);
+    loop {
+      match step_2(&mut map) {
+        flashed if flashed.is_empty() => break,
+        flashed => cells.extend(flashed),
+      }
+    }
+    Step {
+      count: cells.len(),
+      cells,
+    }
+  })
+}
+
+fn get_adjacent(
+  (y, x): (usize, usize),
+  (h, w): (usize, usize),
+) -> impl Iterator<Item = (usize, usize)> {
+  let map_to_adjacent = move |p: (isize, isize)| match p {
+    (0, 0) => None,
+    (m, n) => {
+      let (o, p) = (m + y as isize, n + x as isize);
+      if (0..h as isize).contains(&o) && (0..w as isize).contains(&p) {
+        Some((o as usize, p as usize))
+      } else {
+        None
+      }
+    }
+  };
+  (-1..2).cartesian_product(-1..2).filter_map(map_to_adjacent)
+}
+
+fn step_1(map: &mut TParsed) {
+  map.iter_mut().flatten().for_each(|i| *i += 1);
+}
+
+fn step_2(map: &mut TParsed) -> Vec<(usize, usize)> {
+  let size = (map.len(), map.first().map_or(0, |ln| ln.len()));
+  let mut flashed = vec![vec![0; size.1]; size.0];
+  let mut cells = Vec::new();
+
+  for (y, ln) in map.iter_mut().enumerate() {
+    for (x, n) in ln.iter_mut().enumerate() {
+      if *n > 9 {
+        *n = 0;
+        flashed[y][x] = 1;
+        cells.push((y, x));
+      }
+    }
+  }
+
+  for (y, ln) in map.iter_mut().enumerate() {
+    for (x, n) in ln.iter_mut().enumerate() {
+      if *n > 0 {
+        for (o, p) in get_adjacent((y, x), size) {
+          *n += flashed[o][p];
+        }
+      }
+    }
+  }
+
+  cells
+}
+
+fn parse(input: &str) -> TParsed {
+  match try_parse(input) {
+    Ok((_, v)) => v,
+    Err(e) => panic!("error parsing: {:?}", e),
+  }
+}
+
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
+  all_consuming(verify(
+    separated_list1(newline, parse_line),
+    |rows: &TParsed| rows.iter().map(|ln| ln.len()).all_equal(),
+  ))(i)
+}
+
+fn parse_line(i: &str) -> IResult<&str, TParsedSub> {
+  many1(map_res(take(1u8), |s: &str| s.parse()))(i)
+}
+
+#[test]
+fn show_parse_11() {
+  let input = parse(EXAMPLE_INPUT);
+  println!("{:?}", input);
+}
+
+#[test]
+fn test_example_1_11() {
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(part_1(&input), 1656)
+}
+
+#[test]
+fn test_example_2_11() {
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(part_2(&input), 195)
+}
+
+#[test]
+fn test_steps_11() {
+  let input = parse("11111\n19991\n19191\n19991\n11111");
+  let mut run = steps(&input);
+  let first = run.next().unwrap();
+  assert_eq!(first.count, 9);
+  assert_eq!(
+    first.cells[..8],
+    [
+      (1, 1),
+      (1, 2),
+      (1, 3),
+      (2, 1),
+      (2, 3),
+      (3, 1),
+      (3, 2),
+      (3, 3)
+    ]
+  );
+  assert_eq!(first.cells[8], (2, 2));
+  assert_eq!(
+    run.next().unwrap(),
+    Step {
+      count: 0,
+      cells: vec![]
+    }
+  );
+
+  let input = parse(EXAMPLE_INPUT);
+  let counts: Vec<_> = steps(&input).take(3).map(|s| s.count).collect();
+  assert_eq!(counts, [0, 35, 45]);
+}
+
+#[test]
+fn test_rectangular_11() {
+  let input = parse("999\n999");
+  assert_eq!(steps(&input).next().unwrap().count, 6);
+  assert_eq!(part_2(&input), 1);
+
+  // the top three rows of the example, so the flashes only spread through a 3x10 strip
+  let input = parse(&EXAMPLE_INPUT[..32]);
+  assert_eq!(input.len(), 3);
+  let run: Vec<_> = steps(&input).take(2).collect();
+  assert_eq!(run[0].count, 0);
+  assert!(run[1].cells.iter().all(|&(y, x)| y < 3 && x < 10));
+
+  let input = parse("1\n2\n3\n4\n5\n6\n7\n8\n9\n0\n1\n2");
+  assert_eq!(steps(&input).next().unwrap().cells, [(8, 0), (7, 0)]);
+
+  assert!(try_parse("123\n45").is_err());
+}
+
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "\
+5483143223
+2745854711
+5264556173
+6141336146
+6357385478
+4167524645
+2176841721
+6882881134
+4846848554
+5283751526";
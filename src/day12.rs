@@ -1,281 +1,501 @@
-use nom::character::complete::alpha1;
-use nom::character::complete::char;
-use nom::character::complete::newline;
-use nom::sequence::separated_pair;
-use nom::{combinator::*, multi::separated_list1, IResult};
-use std::collections::HashMap;
-use std::collections::HashSet;
-
-type TParsed = (usize, usize, Vec<Vec<usize>>, Vec<bool>);
-
-pub fn day(input: String) -> (usize, usize) {
-  let parsed_input = parse(&input);
-  let p1 = part_1(&parsed_input);
-  let p2 = part_2(&parsed_input);
-  (p1, p2)
-}
-
-fn part_1((start, end, map, smalls): &TParsed) -> usize {
-  fn dfs<'a>(
-    u: usize,
-    v: usize,
-    current_path: &mut Vec<usize>,
-    visited: &mut Vec<bool>,
-    paths: &mut Vec<Vec<usize>>,
-    input: &Vec<Vec<usize>>,
-    smalls: &Vec<bool>,
-  ) {
-    let small = smalls[u];
-
-    if small {
-      if visited[u] {
-        return;
-      } else {
-        *visited.get_mut(u).expect("small cave not found") = true;
-      }
-    }
-    current_path.push(u);
-
-    if u == v {
-      paths.push(current_path.clone());
-      *visited.get_mut(u).expect("small cave not found") = false;
-      current_path.pop();
-      return;
-    }
-
-    let nexts = input.get(u).expect("Unable to find next paths");
-    for next in nexts {
-      dfs(*next, v, current_path, visited, paths, input, smalls);
-    }
-    current_path.pop();
-
-    if small {
-      *visited.get_mut(u).expect("small cave not found") = false;
-    }
-  }
-
-  let mut paths = Vec::new();
-
-  dfs(
-    *start,
-    *end,
-    &mut Vec::new(),
-    &mut vec![false; smalls.len()],
-    &mut paths,
-    &map,
-    &smalls,
-  );
-
-  paths.len()
-}
-
-fn part_2((start, end, map, smalls): &TParsed) -> usize {
-  fn dfs<'a>(
-    u: usize,
-    v: usize,
-    visited_twice: bool,
-    current_path: &mut Vec<usize>,
-    visited: &mut Vec<bool>,
-    paths: &mut Vec<Vec<usize>>,
-    input: &Vec<Vec<usize>>,
-    smalls: &Vec<bool>,
-    start_n: usize,
-  ) {
-    let small = smalls[u];
-    let terminator = u == start_n || u == v;
-    if small && visited[u] {
-      return;
-    }
-
-    current_path.push(u);
-
-    if u == v {
-      paths.push(current_path.clone());
-      *visited.get_mut(u).expect("small cave not found") = false;
-      current_path.pop();
-      return;
-    }
-
-    let nexts = input.get(u).expect("Unable to find next paths");
-
-    if !terminator && small && !visited_twice {
-      for next in nexts {
-        dfs(
-          *next,
-          v,
-          true,
-          current_path,
-          visited,
-          paths,
-          input,
-          smalls,
-          start_n,
-        );
-      }
-    }
-
-    if small {
-      *visited.get_mut(u).expect("small cave not found") = true;
-    }
-
-    for next in nexts {
-      dfs(
-        *next,
-        v,
-        visited_twice,
-        current_path,
-        visited,
-        paths,
-        input,
-        smalls,
-        start_n,
-      );
-    }
-
-    current_path.pop();
-
-    if small {
-      *visited.get_mut(u).expect("small cave not found") = false;
-    }
-  }
-
-  let mut paths = Vec::new();
-
-  dfs(
-    *start,
-    *end,
-    false,
-    &mut Vec::new(),
-    &mut vec![false; smalls.len()],
-    &mut paths,
-    &map,
-    &smalls,
-    *start,
-  );
-
-  paths.sort_unstable();
-  paths.dedup();
-  paths.len()
-}
-
-fn parse<'a>(input: &'a str) -> TParsed {
-  match try_parse(input) {
-    Ok((_, v)) => make_map(v),
-    Err(e) => panic!("error parsing: {:?}", e),
-  }
-}
-
-fn make_map<'a>(input: Vec<(&'a str, &'a str)>) -> TParsed {
-  let mut res = HashMap::new();
-
-  for i in input {
-    res.entry(i.0).or_insert(HashSet::new()).insert(i.1);
-    res.entry(i.1).or_insert(HashSet::new()).insert(i.0);
-  }
-
-  let lookup = res.iter().map(|(k, _)| *k).collect::<Vec<&str>>();
-  let mut map: Vec<Vec<usize>> = Vec::new();
-
-  for node in lookup.iter() {
-    let nexts = res.get(node).expect("Unable to find next paths");
-
-    map.push(
-      nexts
-        .iter()
-        .map(|s| {
-          lookup
-            .iter()
-            .position(|c| c == s)
-            .expect("Unable to find in lookup")
-        })
-        .collect(),
-    );
-  }
-
-  let is_small = |s: &&str| -> bool { s.chars().any(|c| c.is_ascii_lowercase()) };
-  let smalls = lookup.iter().map(is_small).collect();
-
-  let start = lookup
-    .iter()
-    .position(|&c| c == "start")
-    .expect("Unable to find start in lookup");
-
-  let end = lookup
-    .iter()
-    .position(|&c| c == "end")
-    .expect("Unable to find end in lookup");
-
-  (start, end, map, smalls)
-}
-
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, Vec<(&'a str, &'a str)>> {
-  all_consuming(separated_list1(newline, parse_line))(i)
-}
-
-fn parse_line<'a>(i: &'a str) -> IResult<&'a str, (&'a str, &'a str)> {
-  separated_pair(alpha1, char('-'), alpha1)(i)
-}
-
-#[test]
-fn show_parse_12() {
-  let input = parse(EXAMPLE_INPUT);
-  println!("{:?}", input);
-}
-
-#[test]
-fn test_example_1_12() {
-  assert_eq!(part_1(&parse(EXAMPLE_INPUT)), 10);
-  assert_eq!(part_1(&parse(EXAMPLE_INPUT2)), 19);
-  assert_eq!(part_1(&parse(EXAMPLE_INPUT3)), 226);
-}
-
-#[test]
-fn test_example_2_12() {
-  assert_eq!(part_2(&parse(EXAMPLE_INPUT)), 36);
-  assert_eq!(part_2(&parse(EXAMPLE_INPUT2)), 103);
-  assert_eq!(part_2(&parse(EXAMPLE_INPUT3)), 3509);
-}
-
-#[cfg(test)]
-const EXAMPLE_INPUT: &str = "\
-start-A
-start-b
-A-c
-A-b
-b-d
-A-end
-b-end";
-
-#[cfg(test)]
-const EXAMPLE_INPUT2: &str = "\
-dc-end
-HN-start
-start-kj
-dc-start
-dc-HN
-LN-dc
-HN-end
-kj-sa
-kj-HN
-kj-dc";
-
-#[cfg(test)]
-const EXAMPLE_INPUT3: &str = "\
-fs-end
-he-DX
-fs-he
-start-DX
-pj-DX
-end-zg
-zg-sl
-zg-pj
-pj-he
-RW-he
-fs-DX
-pj-RW
-zg-RW
-start-pj
-he-WI
-zg-he
-pj-fs
-start-RW";
+use nom::character::complete::alpha1;
+use nom::character::complete::char;
+use nom::character::complete::newline;
+use nom::sequence::separated_pair;
+use nom::{combinator::*, multi::separated_list1, IResult};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+type TParsed = CaveGraph;
+
+struct CaveGraph {
+  map: Vec<Vec<usize>>,
+  smalls: Vec<bool>,
+  names: Vec<String>,
+  ids: HashMap<String, usize>,
+}
+
+pub fn day(input: String) -> (usize, usize) {
+  let parsed_input = parse(&input);
+  if let Some(dir) = crate::main_util::output_dir() {
+    write_paths(&parsed_input, &dir.join("day12_paths.txt"))
+      .expect("Unable to write day12_paths.txt");
+    let (start, end) = (parsed_input.cave("start"), parsed_input.cave("end"));
+    let shortest = paths(&parsed_input, start, end, 0).min_by_key(|p| p.len());
+    let dot = parsed_input.to_dot(shortest.as_deref());
+    std::fs::write(dir.join("day12.dot"), dot).expect("Unable to write day12.dot");
+  }
+  let p1 = part_1(&parsed_input);
+  let p2 = part_2(&parsed_input);
+  (p1, p2)
+}
+
+// without revisits there are few enough paths to walk them all, no memo needed
+fn part_1(input: &TParsed) -> usize {
+  count_paths(input, input.cave("start"), input.cave("end"), 0)
+}
+
+fn part_2(input: &TParsed) -> usize {
+  count_paths_memo(input, input.cave("start"), input.cave("end"), 1)
+}
+
+// `ids` is left out: it only mirrors `names`, and its hash order changes from run to run
+impl fmt::Debug for CaveGraph {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CaveGraph")
+      .field("map", &self.map)
+      .field("smalls", &self.smalls)
+      .field("names", &self.names)
+      .finish()
+  }
+}
+
+impl CaveGraph {
+  fn cave(&self, name: &str) -> usize {
+    *self
+      .ids
+      .get(name)
+      .unwrap_or_else(|| panic!("Unable to find {} in lookup", name))
+  }
+
+  fn intern(&mut self, name: &str) -> usize {
+    if let Some(&id) = self.ids.get(name) {
+      return id;
+    }
+
+    let id = self.names.len();
+    self.ids.insert(name.to_owned(), id);
+    self.names.push(name.to_owned());
+    self
+      .smalls
+      .push(name.chars().any(|c| c.is_ascii_lowercase()));
+    self.map.push(Vec::new());
+    id
+  }
+
+  // big caves are boxes and small caves circles; `path` is drawn in red when given
+  fn to_dot(&self, path: Option<&[&str]>) -> String {
+    const HIGHLIGHT: &str = "color=red, penwidth=2";
+
+    let path: Vec<usize> = path
+      .unwrap_or_default()
+      .iter()
+      .map(|c| self.cave(c))
+      .collect();
+    let on_path = |u: usize, v: usize| {
+      path
+        .windows(2)
+        .any(|w| (w[0], w[1]) == (u, v) || (w[0], w[1]) == (v, u))
+    };
+
+    let mut res = String::from("graph caves {\n");
+    for (u, name) in self.names.iter().enumerate() {
+      let shape = if self.smalls[u] { "circle" } else { "box" };
+      if path.contains(&u) {
+        res += &format!("  \"{}\" [shape={}, {}];\n", name, shape, HIGHLIGHT);
+      } else {
+        res += &format!("  \"{}\" [shape={}];\n", name, shape);
+      }
+    }
+    for (u, nexts) in self.map.iter().enumerate() {
+      for &v in nexts.iter().filter(|&&v| u < v) {
+        let (l, r) = (&self.names[u], &self.names[v]);
+        if on_path(u, v) {
+          res += &format!("  \"{}\" -- \"{}\" [{}];\n", l, r, HIGHLIGHT);
+        } else {
+          res += &format!("  \"{}\" -- \"{}\";\n", l, r);
+        }
+      }
+    }
+    res += "}\n";
+    res
+  }
+}
+
+// `revisits` is how many times in total a path may enter a small cave it already passed through
+fn count_paths(input: &TParsed, from: usize, to: usize, revisits: usize) -> usize {
+  // small caves are tracked in a bitmask, so counting never allocates
+  fn dfs(u: usize, visited: u64, revisits: usize, g: (&TParsed, usize, usize)) -> usize {
+    let (CaveGraph { map, smalls, .. }, from, to) = g;
+    if u == to {
+      return 1;
+    }
+
+    map[u]
+      .iter()
+      .map(|&v| {
+        if !smalls[v] || visited & (1 << v) == 0 {
+          dfs(v, visited | (smalls[v] as u64) << v, revisits, g)
+        } else if revisits > 0 && v != from {
+          dfs(v, visited, revisits - 1, g)
+        } else {
+          0
+        }
+      })
+      .sum()
+  }
+
+  assert!(
+    input.map.len() <= 64,
+    "too many caves for the visited bitmask"
+  );
+  dfs(from, 1 << from, revisits, (input, from, to))
+}
+
+// same walk as `count_paths`, memoized on (cave, visited small caves, revisits left)
+fn count_paths_memo(input: &TParsed, from: usize, to: usize, revisits: usize) -> usize {
+  type TMemo = HashMap<(usize, u64, usize), usize>;
+
+  fn dfs(
+    u: usize,
+    visited: u64,
+    revisits: usize,
+    g: (&TParsed, usize, usize),
+    memo: &mut TMemo,
+  ) -> usize {
+    let (CaveGraph { map, smalls, .. }, from, to) = g;
+    if u == to {
+      return 1;
+    }
+    if let Some(&n) = memo.get(&(u, visited, revisits)) {
+      return n;
+    }
+
+    let mut n = 0;
+    for &v in &map[u] {
+      if !smalls[v] || visited & (1 << v) == 0 {
+        n += dfs(v, visited | (smalls[v] as u64) << v, revisits, g, memo);
+      } else if revisits > 0 && v != from {
+        n += dfs(v, visited, revisits - 1, g, memo);
+      }
+    }
+
+    memo.insert((u, visited, revisits), n);
+    n
+  }
+
+  assert!(
+    input.map.len() <= 64,
+    "too many caves for the visited bitmask"
+  );
+  dfs(
+    from,
+    1 << from,
+    revisits,
+    (input, from, to),
+    &mut TMemo::new(),
+  )
+}
+
+fn paths(input: &TParsed, from: usize, to: usize, revisits: usize) -> Paths<'_> {
+  let mut visited = vec![false; input.smalls.len()];
+  visited[from] = true;
+
+  Paths {
+    graph: input,
+    from,
+    to,
+    stack: vec![(from, 0, false)],
+    visited,
+    revisits,
+  }
+}
+
+// one part 1 path per line, streamed so the full list never sits in memory
+fn write_paths(input: &TParsed, path: &Path) -> io::Result<()> {
+  let mut out = BufWriter::new(File::create(path)?);
+  for p in paths(input, input.cave("start"), input.cave("end"), 0) {
+    writeln!(out, "{}", p.join(","))?;
+  }
+  out.flush()
+}
+
+// depth first walk kept on an explicit stack of (cave, next neighbour, entered as revisit)
+struct Paths<'a> {
+  graph: &'a TParsed,
+  from: usize,
+  to: usize,
+  stack: Vec<(usize, usize, bool)>,
+  visited: Vec<bool>,
+  revisits: usize,
+}
+
+impl<'a> Iterator for Paths<'a> {
+  type Item = Vec<&'a str>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let CaveGraph {
+      map, smalls, names, ..
+    } = self.graph;
+
+    loop {
+      let frame = self.stack.last_mut()?;
+      let (u, i) = (frame.0, frame.1);
+      frame.1 += 1;
+
+      let v = match map[u].get(i) {
+        Some(&v) => v,
+        None => {
+          let (_, _, revisit) = self.stack.pop().unwrap();
+          if revisit {
+            self.revisits += 1;
+          } else {
+            self.visited[u] = false;
+          }
+          continue;
+        }
+      };
+
+      if v == self.to {
+        let path = self.stack.iter().map(|f| f.0).chain([v]);
+        return Some(path.map(|c| names[c].as_str()).collect());
+      }
+
+      if !smalls[v] || !self.visited[v] {
+        self.visited[v] = smalls[v];
+        self.stack.push((v, 0, false));
+      } else if self.revisits > 0 && v != self.from {
+        self.revisits -= 1;
+        self.stack.push((v, 0, true));
+      }
+    }
+  }
+}
+
+fn parse(input: &str) -> TParsed {
+  match try_parse(input) {
+    Ok((_, v)) => make_map(v),
+    Err(e) => panic!("error parsing: {:?}", e),
+  }
+}
+
+// caves get ids in the order they first appear, so the layout is the same on every run
+fn make_map(input: Vec<(&str, &str)>) -> TParsed {
+  let mut graph = CaveGraph {
+    map: Vec::new(),
+    smalls: Vec::new(),
+    names: Vec::new(),
+    ids: HashMap::new(),
+  };
+
+  for (l, r) in input {
+    let (l, r) = (graph.intern(l), graph.intern(r));
+    if !graph.map[l].contains(&r) {
+      graph.map[l].push(r);
+      graph.map[r].push(l);
+    }
+  }
+
+  graph
+}
+
+fn try_parse(i: &str) -> IResult<&str, Vec<(&str, &str)>> {
+  all_consuming(separated_list1(newline, parse_line))(i)
+}
+
+fn parse_line(i: &str) -> IResult<&str, (&str, &str)> {
+  separated_pair(alpha1, char('-'), alpha1)(i)
+}
+
+#[test]
+fn show_parse_12() {
+  let input = parse(EXAMPLE_INPUT);
+  println!("{:?}", input);
+}
+
+#[test]
+fn test_example_1_12() {
+  assert_eq!(part_1(&parse(EXAMPLE_INPUT)), 10);
+  assert_eq!(part_1(&parse(EXAMPLE_INPUT2)), 19);
+  assert_eq!(part_1(&parse(EXAMPLE_INPUT3)), 226);
+}
+
+#[test]
+fn test_example_2_12() {
+  assert_eq!(part_2(&parse(EXAMPLE_INPUT)), 36);
+  assert_eq!(part_2(&parse(EXAMPLE_INPUT2)), 103);
+  assert_eq!(part_2(&parse(EXAMPLE_INPUT3)), 3509);
+}
+
+#[test]
+fn test_paths_12() {
+  let input = parse(EXAMPLE_INPUT);
+  let (start, end) = (input.cave("start"), input.cave("end"));
+  let mut listed: Vec<String> = paths(&input, start, end, 0).map(|p| p.join(",")).collect();
+  listed.sort_unstable();
+  assert_eq!(
+    listed,
+    [
+      "start,A,b,A,c,A,end",
+      "start,A,b,A,end",
+      "start,A,b,end",
+      "start,A,c,A,b,A,end",
+      "start,A,c,A,b,end",
+      "start,A,c,A,end",
+      "start,A,end",
+      "start,b,A,c,A,end",
+      "start,b,A,end",
+      "start,b,end",
+    ]
+  );
+
+  for example in [EXAMPLE_INPUT, EXAMPLE_INPUT2, EXAMPLE_INPUT3] {
+    let input = parse(example);
+    let (start, end) = (input.cave("start"), input.cave("end"));
+    for revisits in 0..=1 {
+      let count = count_paths(&input, start, end, revisits);
+      let mut listed: Vec<_> = paths(&input, start, end, revisits).collect();
+      assert_eq!(listed.len(), count);
+      listed.sort_unstable();
+      listed.dedup();
+      assert_eq!(listed.len(), count);
+    }
+  }
+
+  let file = std::env::temp_dir().join("day12_test_paths.txt");
+  write_paths(&input, &file).unwrap();
+  let mut written: Vec<String> = std::fs::read_to_string(&file)
+    .unwrap()
+    .lines()
+    .map(String::from)
+    .collect();
+  std::fs::remove_file(&file).unwrap();
+  written.sort_unstable();
+  assert_eq!(written, listed);
+}
+
+#[test]
+fn test_debug_12() {
+  let debug = format!("{:?}", parse(EXAMPLE_INPUT));
+  assert_eq!(debug, format!("{:?}", parse(EXAMPLE_INPUT)));
+  assert_eq!(
+    debug,
+    "CaveGraph { map: [[1, 2], [0, 3, 2, 5], [0, 1, 4, 5], [1], [2], [1, 2]], \
+     smalls: [true, false, true, true, true, true], \
+     names: [\"start\", \"A\", \"b\", \"c\", \"d\", \"end\"] }"
+  );
+}
+
+#[test]
+fn test_intern_12() {
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(input.names, ["start", "A", "b", "c", "d", "end"]);
+  assert_eq!(input.smalls, [true, false, true, true, true, true]);
+  assert_eq!(
+    input.map,
+    [
+      vec![1, 2],
+      vec![0, 3, 2, 5],
+      vec![0, 1, 4, 5],
+      vec![1],
+      vec![2],
+      vec![1, 2]
+    ]
+  );
+  assert_eq!(input.cave("d"), 4);
+
+  let dup = parse("start-A\nA-start\nA-end");
+  assert_eq!(dup.map, [vec![1], vec![0, 2], vec![1]]);
+}
+
+#[test]
+fn test_to_dot_12() {
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(
+    input.to_dot(Some(&["start", "b", "end"])),
+    "\
+graph caves {
+  \"start\" [shape=circle, color=red, penwidth=2];
+  \"A\" [shape=box];
+  \"b\" [shape=circle, color=red, penwidth=2];
+  \"c\" [shape=circle];
+  \"d\" [shape=circle];
+  \"end\" [shape=circle, color=red, penwidth=2];
+  \"start\" -- \"A\";
+  \"start\" -- \"b\" [color=red, penwidth=2];
+  \"A\" -- \"c\";
+  \"A\" -- \"b\";
+  \"A\" -- \"end\";
+  \"b\" -- \"d\";
+  \"b\" -- \"end\" [color=red, penwidth=2];
+}
+"
+  );
+  assert!(!input.to_dot(None).contains("color=red"));
+}
+
+#[test]
+fn test_count_memo_12() {
+  for example in [EXAMPLE_INPUT, EXAMPLE_INPUT2] {
+    let input = parse(example);
+    let caves = input.names.len();
+    for (from, to) in (0..caves).flat_map(|f| (0..caves).map(move |t| (f, t))) {
+      for revisits in 0..=2 {
+        assert_eq!(
+          count_paths_memo(&input, from, to, revisits),
+          count_paths(&input, from, to, revisits)
+        );
+      }
+    }
+  }
+
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(
+    count_paths_memo(&input, input.cave("b"), input.cave("c"), 0),
+    9
+  );
+
+  let input = parse(EXAMPLE_INPUT3);
+  let (start, end) = (input.cave("start"), input.cave("end"));
+  assert_eq!(
+    count_paths_memo(&input, start, end, 3),
+    count_paths(&input, start, end, 3)
+  );
+}
+
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "\
+start-A
+start-b
+A-c
+A-b
+b-d
+A-end
+b-end";
+
+#[cfg(test)]
+const EXAMPLE_INPUT2: &str = "\
+dc-end
+HN-start
+start-kj
+dc-start
+dc-HN
+LN-dc
+HN-end
+kj-sa
+kj-HN
+kj-dc";
+
+#[cfg(test)]
+const EXAMPLE_INPUT3: &str = "\
+fs-end
+he-DX
+fs-he
+start-DX
+pj-DX
+end-zg
+zg-sl
+zg-pj
+pj-he
+RW-he
+fs-DX
+pj-RW
+zg-RW
+start-pj
+he-WI
+zg-he
+pj-fs
+start-RW";
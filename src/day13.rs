@@ -1,156 +1,499 @@
-use nom::bytes::complete::tag;
-use nom::bytes::complete::take;
-use nom::character::complete::char;
-use nom::character::complete::digit1;
-use nom::character::complete::newline;
-use nom::sequence::pair;
-use nom::sequence::preceded;
-use nom::sequence::separated_pair;
-use nom::{combinator::*, multi::separated_list1, IResult};
-use std::collections::HashSet;
-use vek::vec::Vec2;
-
-type TParsed = (TParsedSub, TParsedSub);
-type TParsedSub = Vec<TParsedSubSub>;
-type TParsedSubSub = Vec2<isize>;
-
-pub fn day<'a>(input: String) -> (usize, &'a str) {
-  let parsed_input = parse(&input);
-  let p1 = part_1(&parsed_input);
-  part_2(&parsed_input);
-  (p1, "PGHRKLKL")
-}
-
-fn part_1((coords, folds): &TParsed) -> usize {
-  let mut coords = coords.clone();
-  let fold = folds[0];
-  for coord in &mut coords {
-    if fold.x > 0 && fold.x < coord.x {
-      coord.x = fold.x - (coord.x - fold.x);
-    } else if fold.y > 0 && fold.y < coord.y {
-      coord.y = fold.y - (coord.y - fold.y);
-    }
-  }
-
-  coords.iter().collect::<HashSet<&Vec2<isize>>>().len()
-}
-
-fn part_2((coords, folds): &TParsed) {
-  let mut coords = coords.clone();
-  for fold in folds {
-    for coord in &mut coords {
-      if fold.x > 0 && fold.x < coord.x {
-        coord.x = fold.x - (coord.x - fold.x);
-      } else if fold.y > 0 && fold.y < coord.y {
-        coord.y = fold.y - (coord.y - fold.y);
-      }
-    }
-  }
-
-  let ymax = coords.iter().map(|v| v.y).max().expect("No ymax") + 1;
-  let xmax = coords.iter().map(|v| v.x).max().expect("No xmax") + 1;
-
-  for y in 0..ymax {
-    for x in 0..xmax {
-      let c = if coords.iter().any(|v| v.x == x && v.y == y) {
-        "#"
-      } else {
-        "."
-      };
-      print!("{}", c);
-    }
-    println!("");
-  }
-}
-
-fn parse<'a>(input: &'a str) -> TParsed {
-  match try_parse(input) {
-    Ok((_, v)) => v,
-    Err(e) => panic!("error parsing: {:?}", e),
-  }
-}
-
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
-  all_consuming(separated_pair(
-    parse_coords,
-    pair(newline, newline),
-    parse_folds,
-  ))(i)
-}
-
-fn parse_coords<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
-  separated_list1(newline, parse_coord)(i)
-}
-
-fn parse_coord<'a>(i: &'a str) -> IResult<&'a str, TParsedSubSub> {
-  map(
-    separated_pair(get_dig, char(','), get_dig),
-    |(x, y): (isize, isize)| Vec2::new(x, y),
-  )(i)
-}
-
-fn parse_folds<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
-  separated_list1(newline, parse_fold)(i)
-}
-
-fn parse_fold<'a>(i: &'a str) -> IResult<&'a str, TParsedSubSub> {
-  preceded(
-    tag("fold along "),
-    map(
-      separated_pair(take(1u8), char('='), get_dig),
-      |(axis, n): (&'a str, isize)| match axis {
-        "x" => Vec2::new(n, 0),
-        _ => Vec2::new(0, n),
-      },
-    ),
-  )(i)
-}
-
-fn get_dig<'a, A>(i: &'a str) -> IResult<&'a str, A>
-where
-  A: std::str::FromStr,
-{
-  map_res(digit1, |x: &str| x.parse())(i)
-}
-
-#[test]
-fn show_parse_13() {
-  let input = parse(EXAMPLE_INPUT);
-  println!("{:?}", input);
-}
-
-#[test]
-fn test_example_1_13() {
-  let input = parse(EXAMPLE_INPUT);
-  assert_eq!(part_1(&input), 17)
-}
-
-#[test]
-fn test_example_2_13() {
-  let input = parse(EXAMPLE_INPUT);
-  part_2(&input);
-}
-
-#[cfg(test)]
-const EXAMPLE_INPUT: &str = "\
-6,10
-0,14
-9,10
-0,3
-10,4
-4,11
-6,0
-6,12
-4,1
-0,13
-10,12
-3,4
-3,0
-8,4
-1,10
-2,14
-8,10
-9,0
-
-fold along y=7
-fold along x=5";
+use crate::ocr;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::char;
+use nom::character::complete::digit1;
+use nom::character::complete::newline;
+use nom::sequence::pair;
+use nom::sequence::preceded;
+use nom::sequence::separated_pair;
+use nom::{combinator::*, multi::separated_list1, IResult};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use vek::vec::Vec2;
+
+type TParsed = (TParsedSub, Vec<Fold>);
+type TParsedSub = Vec<TParsedSubSub>;
+type TParsedSubSub = Vec2<isize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+  X,
+  Y,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fold {
+  axis: Axis,
+  at: isize,
+}
+
+pub fn day(input: String) -> (usize, String) {
+  let parsed_input = parse(&input);
+  let p1 = part_1(&parsed_input);
+  let sheet = part_2(&parsed_input);
+  if let Some(dir) = crate::main_util::output_dir() {
+    sheet
+      .write_pbm(&dir.join("day13.pbm"))
+      .expect("Unable to write day13.pbm");
+    fs::write(dir.join("day13.txt"), sheet.to_blocks()).expect("Unable to write day13.txt");
+    let frames = Animation::Pbm(dir.join("day13_frames"));
+    animate(&parsed_input, frames, &mut io::stdout()).expect("Unable to write day13 frames");
+  }
+  if let Some(delay) = crate::main_util::env_arg("AOC_ANIMATE") {
+    let terminal = Animation::Terminal(Some(Duration::from_millis(delay)));
+    animate(&parsed_input, terminal, &mut io::stdout()).expect("Unable to play day13 frames");
+  }
+  let p2 = sheet.letters();
+  (p1, p2)
+}
+
+fn part_1((coords, folds): &TParsed) -> usize {
+  apply_fold(coords.iter().copied(), folds[0]).len()
+}
+
+fn part_2(input: &TParsed) -> Bitmap {
+  fold_frames(input).pop().unwrap()
+}
+
+// the sheet before any fold, followed by the sheet after each fold in turn
+fn fold_frames((coords, folds): &TParsed) -> Vec<Bitmap> {
+  let mut dots: HashSet<_> = coords.iter().copied().collect();
+  let mut frames = vec![Bitmap::new(dots.iter().copied())];
+  for &fold in folds {
+    dots = apply_fold(dots, fold);
+    frames.push(Bitmap::new(dots.iter().copied()));
+  }
+  frames
+}
+
+enum Animation {
+  Terminal(Option<Duration>),
+  Pbm(PathBuf),
+}
+
+// plays the folds on `out`, or writes them as numbered PBM files and reports where they went
+fn animate<W: Write>(input: &TParsed, animation: Animation, out: &mut W) -> io::Result<()> {
+  let frames = fold_frames(input);
+  match animation {
+    Animation::Terminal(delay) => show_frames(&frames, out, delay),
+    Animation::Pbm(dir) => {
+      let paths = write_frames(&frames, &dir)?;
+      writeln!(out, "wrote {} frames to {}", paths.len(), dir.display())
+    }
+  }
+}
+
+// redraws each frame in place, so a delay between them plays as an animation
+fn show_frames<W: Write>(
+  frames: &[Bitmap],
+  out: &mut W,
+  delay: Option<Duration>,
+) -> io::Result<()> {
+  for (i, frame) in frames.iter().enumerate() {
+    write!(out, "\x1b[2J\x1b[H")?;
+    writeln!(out, "fold {}/{}", i, frames.len() - 1)?;
+    write!(out, "{}", frame.to_blocks())?;
+    out.flush()?;
+    if let Some(delay) = delay {
+      thread::sleep(delay);
+    }
+  }
+  Ok(())
+}
+
+// numbered so the files sort in fold order, e.g. for `convert -delay 50 frame_*.pbm folds.gif`
+fn write_frames(frames: &[Bitmap], dir: &Path) -> io::Result<Vec<PathBuf>> {
+  fs::create_dir_all(dir)?;
+  frames
+    .iter()
+    .enumerate()
+    .map(|(i, frame)| {
+      let path = dir.join(format!("frame_{:03}.pbm", i));
+      fs::write(&path, frame.to_pbm())?;
+      Ok(path)
+    })
+    .collect()
+}
+
+// mirrors every dot past the fold line onto the near side; dots on the line itself stay put
+fn apply_fold<I: IntoIterator<Item = TParsedSubSub>>(
+  dots: I,
+  fold: Fold,
+) -> HashSet<TParsedSubSub> {
+  let mirror = |v: isize| if v > fold.at { 2 * fold.at - v } else { v };
+  dots
+    .into_iter()
+    .map(|d| match fold.axis {
+      Axis::X => Vec2::new(mirror(d.x), d.y),
+      Axis::Y => Vec2::new(d.x, mirror(d.y)),
+    })
+    .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Bitmap {
+  dots: HashSet<TParsedSubSub>,
+  width: isize,
+  height: isize,
+}
+
+impl Bitmap {
+  fn new<I: IntoIterator<Item = TParsedSubSub>>(dots: I) -> Bitmap {
+    // a fold near the edge can flip dots to negative coordinates, so shift them back into view
+    let dots: HashSet<_> = dots.into_iter().collect();
+    let min_x = dots.iter().map(|v| v.x).min().unwrap_or(0).min(0);
+    let min_y = dots.iter().map(|v| v.y).min().unwrap_or(0).min(0);
+    let dots: HashSet<_> = dots.iter().map(|v| *v - Vec2::new(min_x, min_y)).collect();
+    let width = dots.iter().map(|v| v.x + 1).max().unwrap_or(0);
+    let height = dots.iter().map(|v| v.y + 1).max().unwrap_or(0);
+    Bitmap {
+      dots,
+      width,
+      height,
+    }
+  }
+
+  fn get(&self, x: isize, y: isize) -> bool {
+    self.dots.contains(&Vec2::new(x, y))
+  }
+
+  fn letters(&self) -> String {
+    ocr::parse_letters(
+      self.dots.iter().map(|v| (v.x as usize, v.y as usize)),
+      (0, 0),
+    )
+  }
+
+  // two rows per line with half blocks, so letters keep their proportions in a terminal
+  fn to_blocks(&self) -> String {
+    let mut res = String::new();
+    for y in (0..self.height).step_by(2) {
+      for x in 0..self.width {
+        res.push(match (self.get(x, y), self.get(x, y + 1)) {
+          (true, true) => '█',
+          (true, false) => '▀',
+          (false, true) => '▄',
+          (false, false) => ' ',
+        });
+      }
+      res.push('\n');
+    }
+    res
+  }
+
+  fn to_pbm(&self) -> String {
+    let mut res = format!("P1\n{} {}\n", self.width, self.height);
+    for y in 0..self.height {
+      for x in 0..self.width {
+        res.push(if self.get(x, y) { '1' } else { '0' });
+      }
+      res.push('\n');
+    }
+    res
+  }
+
+  fn write_pbm(&self, path: &Path) -> std::io::Result<()> {
+    fs::write(path, self.to_pbm())
+  }
+}
+
+impl fmt::Display for Bitmap {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for y in 0..self.height {
+      for x in 0..self.width {
+        write!(f, "{}", if self.get(x, y) { '#' } else { '.' })?;
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
+
+fn parse(input: &str) -> TParsed {
+  match try_parse(input) {
+    Ok((_, v)) => v,
+    Err(e) => panic!("error parsing: {:?}", e),
+  }
+}
+
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
+  all_consuming(separated_pair(
+    parse_coords,
+    pair(newline, newline),
+    parse_folds,
+  ))(i)
+}
+
+fn parse_coords(i: &str) -> IResult<&str, TParsedSub> {
+  separated_list1(newline, parse_coord)(i)
+}
+
+fn parse_coord(i: &str) -> IResult<&str, TParsedSubSub> {
+  map(
+    separated_pair(get_dig, char(','), get_dig),
+    |(x, y): (isize, isize)| Vec2::new(x, y),
+  )(i)
+}
+
+fn parse_folds(i: &str) -> IResult<&str, Vec<Fold>> {
+  separated_list1(newline, parse_fold)(i)
+}
+
+fn parse_fold(i: &str) -> IResult<&str, Fold> {
+  preceded(
+    tag("fold along "),
+    map(
+      separated_pair(parse_axis, char('='), get_dig),
+      |(axis, at)| Fold { axis, at },
+    ),
+  )(i)
+}
+
+fn parse_axis(i: &str) -> IResult<&str, Axis> {
+  alt((value(Axis::X, char('x')), value(Axis::Y, char('y'))))(i)
+}
+
+fn get_dig<A>(i: &str) -> IResult<&str, A>
+where
+  A: std::str::FromStr,
+{
+  map_res(digit1, |x: &str| x.parse())(i)
+}
+
+#[test]
+fn show_parse_13() {
+  let input = parse(EXAMPLE_INPUT);
+  println!("{:?}", input);
+}
+
+#[test]
+fn test_example_1_13() {
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(part_1(&input), 17)
+}
+
+#[test]
+fn test_example_2_13() {
+  let input = parse(EXAMPLE_INPUT);
+  let sheet = part_2(&input);
+  assert_eq!(sheet.letters(), "?");
+  assert_eq!(
+    sheet.to_string(),
+    "\
+#####
+#...#
+#...#
+#...#
+#####
+"
+  );
+}
+
+#[test]
+fn test_bitmap_13() {
+  let sheet = part_2(&parse(EXAMPLE_INPUT));
+  assert_eq!(sheet.to_blocks(), "█▀▀▀█\n█   █\n▀▀▀▀▀\n");
+  assert_eq!(
+    sheet.to_pbm(),
+    "P1\n5 5\n11111\n10001\n10001\n10001\n11111\n"
+  );
+
+  let path = std::env::temp_dir().join("day13_test.pbm");
+  sheet.write_pbm(&path).unwrap();
+  assert_eq!(std::fs::read_to_string(&path).unwrap(), sheet.to_pbm());
+  std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_fold_frames_13() {
+  let input = parse(EXAMPLE_INPUT);
+  let frames = fold_frames(&input);
+  assert_eq!(frames.len(), 3);
+  assert_eq!((frames[0].width, frames[0].height), (11, 15));
+  assert_eq!(frames[0].dots.len(), 18);
+  assert_eq!(frames[1].dots.len(), part_1(&input));
+  assert_eq!(frames[2], part_2(&input));
+
+  let mut out = Vec::new();
+  show_frames(&frames, &mut out, None).unwrap();
+  let out = String::from_utf8(out).unwrap();
+  assert_eq!(out.matches("\x1b[2J").count(), 3);
+  assert!(out.contains("fold 0/2\n"));
+  assert!(out.ends_with("fold 2/2\n█▀▀▀█\n█   █\n▀▀▀▀▀\n"));
+
+  let dir = std::env::temp_dir().join("day13_test_frames");
+  let paths = write_frames(&frames, &dir).unwrap();
+  assert_eq!(paths.len(), 3);
+  assert!(paths[2].ends_with("frame_002.pbm"));
+  assert_eq!(fs::read_to_string(&paths[2]).unwrap(), frames[2].to_pbm());
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_animate_13() {
+  let input = parse(EXAMPLE_INPUT);
+
+  let mut shown = Vec::new();
+  let delay = Duration::from_millis(5);
+  let start = std::time::Instant::now();
+  animate(&input, Animation::Terminal(Some(delay)), &mut shown).unwrap();
+  assert!(start.elapsed() >= delay * 3);
+  let mut expected = Vec::new();
+  show_frames(&fold_frames(&input), &mut expected, None).unwrap();
+  assert_eq!(shown, expected);
+
+  let dir = std::env::temp_dir().join("day13_test_animate");
+  let mut report = Vec::new();
+  animate(&input, Animation::Pbm(dir.clone()), &mut report).unwrap();
+  assert_eq!(
+    String::from_utf8(report).unwrap(),
+    format!("wrote 3 frames to {}\n", dir.display())
+  );
+  let mut files: Vec<_> = fs::read_dir(&dir)
+    .unwrap()
+    .map(|e| e.unwrap().file_name().into_string().unwrap())
+    .collect();
+  files.sort();
+  assert_eq!(files, ["frame_000.pbm", "frame_001.pbm", "frame_002.pbm"]);
+  assert_eq!(
+    fs::read_to_string(dir.join("frame_002.pbm")).unwrap(),
+    part_2(&input).to_pbm()
+  );
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_apply_fold_13() {
+  let dots = [
+    Vec2::new(0, 0),
+    Vec2::new(2, 1),
+    Vec2::new(4, 2),
+    Vec2::new(3, 3),
+  ];
+
+  let folded = apply_fold(
+    dots,
+    Fold {
+      axis: Axis::X,
+      at: 2,
+    },
+  );
+  let expected = [
+    Vec2::new(0, 0),
+    Vec2::new(2, 1),
+    Vec2::new(0, 2),
+    Vec2::new(1, 3),
+  ];
+  assert_eq!(folded, HashSet::from(expected));
+
+  // everything right of x=0 flips over to the negative side
+  let folded = apply_fold(
+    dots,
+    Fold {
+      axis: Axis::X,
+      at: 0,
+    },
+  );
+  let expected = [
+    Vec2::new(0, 0),
+    Vec2::new(-2, 1),
+    Vec2::new(-4, 2),
+    Vec2::new(-3, 3),
+  ];
+  assert_eq!(folded, HashSet::from(expected));
+  assert_eq!(
+    Bitmap::new(folded).to_string(),
+    "....#\n..#..\n#....\n.#...\n"
+  );
+
+  let folded = apply_fold(
+    dots,
+    Fold {
+      axis: Axis::Y,
+      at: 0,
+    },
+  );
+  assert_eq!(folded.len(), 4);
+  assert!(folded.iter().all(|d| d.y <= 0));
+
+  // dots on the line merge with nothing and stay where they are
+  let folded = apply_fold(
+    dots,
+    Fold {
+      axis: Axis::Y,
+      at: 1,
+    },
+  );
+  let expected = [
+    Vec2::new(0, 0),
+    Vec2::new(2, 1),
+    Vec2::new(4, 0),
+    Vec2::new(3, -1),
+  ];
+  assert_eq!(folded, HashSet::from(expected));
+
+  assert_eq!(
+    parse("0,0\n\nfold along x=0\nfold along y=3").1,
+    vec![
+      Fold {
+        axis: Axis::X,
+        at: 0
+      },
+      Fold {
+        axis: Axis::Y,
+        at: 3
+      }
+    ]
+  );
+}
+
+#[test]
+fn test_letters_13() {
+  // `I` leaves its first column empty, so the sheet has to hand the OCR its real origin
+  let drawing = [
+    ".###..##.",
+    "..#..#..#",
+    "..#..#..#",
+    "..#..####",
+    "..#..#..#",
+    ".###.#..#",
+  ];
+  let sheet = Bitmap::new(drawing.iter().enumerate().flat_map(|(y, row)| {
+    row
+      .chars()
+      .enumerate()
+      .filter(|(_, c)| *c == '#')
+      .map(move |(x, _)| Vec2::new(x as isize, y as isize))
+  }));
+  assert_eq!(sheet.letters(), "IA");
+}
+
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "\
+6,10
+0,14
+9,10
+0,3
+10,4
+4,11
+6,0
+6,12
+4,1
+0,13
+10,12
+3,4
+3,0
+8,4
+1,10
+2,14
+8,10
+9,0
+
+fold along y=7
+fold along x=5";
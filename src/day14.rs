@@ -8,90 +8,335 @@ use nom::sequence::pair;
 use nom::sequence::separated_pair;
 use nom::{combinator::*, multi::separated_list1, IResult};
 use std::collections::HashMap;
+use std::fmt;
 
 type TParsed = (TParsedSub1, TParsedSub2);
 type TParsedSub1 = Vec<char>;
 type TParsedSub2 = HashMap<(char, char), char>;
 
-pub fn day<'a>(input: String) -> (usize, usize) {
+pub fn day(input: String) -> (usize, usize) {
   let parsed_input = parse(&input);
+  if let Some(steps) = crate::main_util::env_arg::<u64>("AOC_STEPS") {
+    let (template, rules) = &parsed_input;
+    match crate::main_util::env_arg("AOC_MODULUS") {
+      Some(modulus) => {
+        let counts = element_counts_mod(template, rules, steps, modulus);
+        println!(
+          "Day 14 after {} steps, mod {}: {}",
+          steps,
+          modulus,
+          show_counts(&counts)
+        );
+      }
+      None => {
+        let counts =
+          element_counts::<BigCount>(template, rules, steps as usize).expect("counts overflowed");
+        println!("Day 14 after {} steps: {}", steps, show_counts(&counts));
+      }
+    }
+  }
   let p1 = part_1(&parsed_input);
   let p2 = part_2(&parsed_input);
   (p1, p2)
 }
 
 fn part_1((template, rules): &TParsed) -> usize {
-  let mut res = template.clone();
+  let counts = element_counts(template, rules, 10).expect("counts overflowed");
+  spread(&counts) as usize
+}
 
-  for _ in 0..10 {
-    let mut res2 = vec![res[0]];
-    for wnd in res.into_iter().tuple_windows() {
-      res2.push(rules[&wnd]);
-      res2.push(wnd.1);
-    }
-    res = res2;
+fn part_2((template, rules): &TParsed) -> usize {
+  let counts = element_counts(template, rules, 40).expect("counts overflowed");
+  spread(&counts) as usize
+}
+
+// a pair without a rule stays as it is
+fn expand(rules: &TParsedSub2, pair: (char, char)) -> impl Iterator<Item = (char, char)> {
+  let (first, second) = match rules.get(&pair) {
+    Some(&c) => ((pair.0, c), Some((c, pair.1))),
+    None => (pair, None),
+  };
+  std::iter::once(first).chain(second)
+}
+
+// element counts in alphabetical order, e.g. "B=1749 C=298"
+fn show_counts<C: fmt::Display>(counts: &HashMap<char, C>) -> String {
+  counts
+    .iter()
+    .sorted_by_key(|(c, _)| **c)
+    .map(|(c, n)| format!("{}={}", c, n))
+    .join(" ")
+}
+
+#[derive(Debug, PartialEq)]
+struct Overflow {
+  step: usize,
+}
+
+// what `element_counts` can count in; fixed-width counts report overflow by returning None
+trait Count: Clone + Default {
+  fn one() -> Self;
+  fn try_add(&self, other: &Self) -> Option<Self>;
+}
+
+impl Count for u128 {
+  fn one() -> Self {
+    1
   }
 
-  let mut arr = vec![0; 26];
-  for c in res {
-    arr[(c as usize) - 65] += 1;
+  fn try_add(&self, other: &Self) -> Option<Self> {
+    self.checked_add(*other)
   }
+}
 
-  arr.iter().max().unwrap() - arr.iter().filter(|&&n| n > 0).min().unwrap()
+// unbounded count as little-endian 64 bit limbs, for step counts where u128 runs out
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BigCount(Vec<u64>);
+
+impl Count for BigCount {
+  fn one() -> Self {
+    BigCount(vec![1])
+  }
+
+  fn try_add(&self, other: &Self) -> Option<Self> {
+    let (long, short) = if self.0.len() >= other.0.len() {
+      (&self.0, &other.0)
+    } else {
+      (&other.0, &self.0)
+    };
+    let mut res = Vec::with_capacity(long.len() + 1);
+    let mut carry = false;
+    for (i, &l) in long.iter().enumerate() {
+      let (sum, c1) = l.overflowing_add(short.get(i).copied().unwrap_or(0));
+      let (sum, c2) = sum.overflowing_add(carry as u64);
+      res.push(sum);
+      carry = c1 || c2;
+    }
+    if carry {
+      res.push(1);
+    }
+    Some(BigCount(res))
+  }
 }
 
-fn part_2((template, rules): &TParsed) -> usize {
-  let mut pairs = HashMap::new();
+impl fmt::Display for BigCount {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    const CHUNK: u128 = 10_000_000_000_000_000_000;
+
+    // peel off 19 decimal digits at a time, lowest first
+    let mut limbs = self.0.clone();
+    let mut chunks = Vec::new();
+    while limbs.iter().any(|&l| l > 0) {
+      let mut rem = 0u128;
+      for l in limbs.iter_mut().rev() {
+        let cur = rem << 64 | *l as u128;
+        *l = (cur / CHUNK) as u64;
+        rem = cur % CHUNK;
+      }
+      chunks.push(rem as u64);
+    }
 
-  for wnd in template.clone().into_iter().tuple_windows() {
-    *pairs.entry(wnd).or_insert(0) += 1;
+    match chunks.split_last() {
+      None => write!(f, "0"),
+      Some((top, rest)) => {
+        write!(f, "{}", top)?;
+        for c in rest.iter().rev() {
+          write!(f, "{:019}", c)?;
+        }
+        Ok(())
+      }
+    }
   }
+}
 
-  for _ in 0..40 {
+// counts pairs instead of building the polymer; every element is the first of exactly one pair,
+// except the last one of the template, which never changes
+fn element_counts<C: Count>(
+  template: &[char],
+  rules: &TParsedSub2,
+  steps: usize,
+) -> Result<HashMap<char, C>, Overflow> {
+  let mut pairs: HashMap<(char, char), C> = HashMap::new();
+  for wnd in template.iter().copied().tuple_windows() {
+    let e = pairs.entry(wnd).or_default();
+    *e = e.try_add(&C::one()).ok_or(Overflow { step: 0 })?;
+  }
+
+  for step in 1..=steps {
     let mut npairs = HashMap::new();
     for (pair, n) in pairs {
-      let c = rules[&pair];
-      *npairs.entry((pair.0, c)).or_insert(0) += n;
-      *npairs.entry((c, pair.1)).or_insert(0) += n;
+      for p in expand(rules, pair) {
+        let e: &mut C = npairs.entry(p).or_default();
+        *e = e.try_add(&n).ok_or(Overflow { step })?;
+      }
     }
     pairs = npairs;
   }
 
-  let mut arr = vec![0; 26];
-  for (&(c1, c2), n) in pairs.iter() {
-    arr[(c1 as usize) - 65] += n;
-    arr[(c2 as usize) - 65] += n;
+  // the tally can overflow even when every pair count fits
+  let mut counts: HashMap<char, C> = HashMap::new();
+  for (&(c, _), n) in pairs.iter() {
+    let e = counts.entry(c).or_default();
+    *e = e.try_add(n).ok_or(Overflow { step: steps })?;
+  }
+  if let Some(&last) = template.last() {
+    let e = counts.entry(last).or_default();
+    *e = e.try_add(&C::one()).ok_or(Overflow { step: steps })?;
+  }
+
+  Ok(counts)
+}
+
+type TMatrix = Vec<Vec<u64>>;
+
+// one step of pair counting is linear, so `steps` of them are the transition matrix raised to
+// `steps`, which repeated squaring gets in O(log steps) multiplications; counts are mod `modulus`
+fn element_counts_mod(
+  template: &[char],
+  rules: &TParsedSub2,
+  steps: u64,
+  modulus: u64,
+) -> HashMap<char, u64> {
+  assert!(modulus > 0, "modulus must be positive");
+  let mut index: Vec<(char, char)> = template.iter().copied().tuple_windows().collect();
+  let mut i = 0;
+  while i < index.len() {
+    for p in expand(rules, index[i]) {
+      if !index.contains(&p) {
+        index.push(p);
+      }
+    }
+    i += 1;
+  }
+  index.sort_unstable();
+  index.dedup();
+  let pos = |p: &(char, char)| index.binary_search(p).unwrap();
+
+  let mut step = vec![vec![0; index.len()]; index.len()];
+  for pair in &index {
+    for p in expand(rules, *pair) {
+      step[pos(&p)][pos(pair)] += 1;
+    }
+  }
+
+  let mut pairs = vec![0; index.len()];
+  for wnd in template.iter().copied().tuple_windows() {
+    pairs[pos(&wnd)] += 1;
+  }
+
+  let mut n = steps;
+  while n > 0 {
+    if n & 1 == 1 {
+      pairs = mat_vec(&step, &pairs, modulus);
+    }
+    step = mat_mul(&step, &step, modulus);
+    n >>= 1;
+  }
+
+  let mut counts = HashMap::new();
+  for (&(c, _), n) in index.iter().zip(pairs) {
+    let e = counts.entry(c).or_insert(0);
+    *e = add_mod(*e, n, modulus);
+  }
+  if let Some(&last) = template.last() {
+    let e = counts.entry(last).or_insert(0);
+    *e = add_mod(*e, 1, modulus);
   }
 
-  arr
-    .iter_mut()
-    .for_each(|n| *n = (*n as f64 / 2.0).ceil() as usize);
+  counts
+}
+
+// the sums below stay in u128: every term is reduced first, so even a modulus near 2^64 fits
+fn add_mod(a: u64, b: u64, modulus: u64) -> u64 {
+  ((a as u128 + b as u128) % modulus as u128) as u64
+}
+
+fn mat_mul(a: &TMatrix, b: &TMatrix, modulus: u64) -> TMatrix {
+  (0..a.len())
+    .map(|r| {
+      (0..b[0].len())
+        .map(|c| {
+          let sum: u128 = (0..b.len())
+            .map(|k| a[r][k] as u128 * b[k][c] as u128 % modulus as u128)
+            .sum();
+          (sum % modulus as u128) as u64
+        })
+        .collect()
+    })
+    .collect()
+}
+
+fn mat_vec(a: &TMatrix, v: &[u64], modulus: u64) -> Vec<u64> {
+  a.iter()
+    .map(|row| {
+      let sum: u128 = row
+        .iter()
+        .zip(v)
+        .map(|(&x, &y)| x as u128 * y as u128 % modulus as u128)
+        .sum();
+      (sum % modulus as u128) as u64
+    })
+    .collect()
+}
+
+fn spread(counts: &HashMap<char, u128>) -> u128 {
+  counts.values().max().unwrap_or(&0) - counts.values().min().unwrap_or(&0)
+}
+
+type TRule = ((char, char), char);
 
-  arr.iter().max().unwrap() - arr.iter().filter(|&&n| n > 0).min().unwrap()
+#[derive(Debug, PartialEq)]
+enum RuleError {
+  Duplicate((char, char), char),
+  Conflict((char, char), char, char),
+}
+
+impl fmt::Display for RuleError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RuleError::Duplicate((l, r), c) => write!(f, "rule {}{} -> {} is listed twice", l, r, c),
+      RuleError::Conflict((l, r), c1, c2) => {
+        write!(f, "pair {}{} inserts both {} and {}", l, r, c1, c2)
+      }
+    }
+  }
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
-    Ok((_, v)) => v,
+    Ok((_, (template, rules))) => match validate_rules(rules) {
+      Ok(rules) => (template, rules),
+      Err(e) => panic!("error parsing: {}", e),
+    },
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn validate_rules(rules: Vec<TRule>) -> Result<TParsedSub2, RuleError> {
+  let mut res = TParsedSub2::new();
+  for (pair, c) in rules {
+    match res.insert(pair, c) {
+      Some(prev) if prev == c => return Err(RuleError::Duplicate(pair, c)),
+      Some(prev) => return Err(RuleError::Conflict(pair, prev, c)),
+      None => (),
+    }
+  }
+  Ok(res)
+}
+
+fn try_parse(i: &str) -> IResult<&str, (TParsedSub1, Vec<TRule>)> {
   all_consuming(separated_pair(parse_templ, many1(newline), parse_rules))(i)
 }
 
-fn parse_templ<'a>(i: &'a str) -> IResult<&'a str, TParsedSub1> {
+fn parse_templ(i: &str) -> IResult<&str, TParsedSub1> {
   map(alpha1, |s: &str| s.chars().collect())(i)
 }
 
-fn parse_rules<'a>(i: &'a str) -> IResult<&'a str, TParsedSub2> {
-  map(separated_list1(newline, parse_rule), |r| {
-    HashMap::from_iter(r)
-  })(i)
+fn parse_rules(i: &str) -> IResult<&str, Vec<TRule>> {
+  separated_list1(newline, parse_rule)(i)
 }
 
-fn parse_rule<'a>(i: &'a str) -> IResult<&'a str, ((char, char), char)> {
+fn parse_rule(i: &str) -> IResult<&str, TRule> {
   separated_pair(pair(anychar, anychar), tag(" -> "), anychar)(i)
 }
 
@@ -113,6 +358,172 @@ fn test_example_2_14() {
   assert_eq!(part_2(&input), 2188189693529)
 }
 
+#[test]
+fn test_element_counts_14() {
+  let (template, rules) = parse(EXAMPLE_INPUT);
+  let counts = element_counts(&template, &rules, 10).unwrap();
+  assert_eq!(
+    counts,
+    HashMap::from([('B', 1749), ('C', 298), ('H', 161), ('N', 865)])
+  );
+  assert_eq!(
+    element_counts::<u128>(&template, &rules, 0).unwrap()[&'N'],
+    2
+  );
+
+  // every pair count still fits after 128 steps, but the per-element sums no longer do
+  assert!(element_counts::<u128>(&template, &rules, 127).is_ok());
+  assert_eq!(
+    element_counts::<u128>(&template, &rules, 128),
+    Err(Overflow { step: 128 })
+  );
+  assert_eq!(
+    element_counts::<u128>(&template, &rules, 5000),
+    Err(Overflow { step: 129 })
+  );
+
+  let template = ['ä', 'z'];
+  let rules = HashMap::from([
+    (('ä', 'z'), '1'),
+    (('ä', '1'), 'z'),
+    (('1', 'z'), 'ä'),
+    (('ä', 'ä'), 'z'),
+    (('z', '1'), 'ä'),
+    (('z', 'z'), '1'),
+  ]);
+  let counts = element_counts(&template, &rules, 1).unwrap();
+  assert_eq!(counts, HashMap::from([('ä', 1), ('1', 1), ('z', 1)]));
+  let counts = element_counts(&template, &rules, 2).unwrap();
+  assert_eq!(counts, HashMap::from([('ä', 2), ('1', 1), ('z', 2)]));
+}
+
+#[test]
+fn test_big_counts_14() {
+  let (template, rules) = parse(EXAMPLE_INPUT);
+
+  let exact = element_counts::<u128>(&template, &rules, 40).unwrap();
+  let big = element_counts::<BigCount>(&template, &rules, 40).unwrap();
+  for (c, n) in exact {
+    assert_eq!(big[&c].to_string(), n.to_string());
+  }
+
+  // the polymer is (len - 1) * 2^steps + 1 long
+  let counts = element_counts::<BigCount>(&template, &rules, 5000).unwrap();
+  let total = counts
+    .values()
+    .fold(BigCount::default(), |l, r| l.try_add(r).unwrap());
+  let mut len = BigCount(vec![template.len() as u64 - 1]);
+  for _ in 0..5000 {
+    len = len.try_add(&len).unwrap();
+  }
+  assert_eq!(total, len.try_add(&BigCount::one()).unwrap());
+  assert_eq!(total.to_string().len(), 1506);
+
+  let mut two_128 = BigCount::one();
+  for _ in 0..128 {
+    two_128 = two_128.try_add(&two_128).unwrap();
+  }
+  assert_eq!(two_128.0, [0, 0, 1]);
+  assert_eq!(
+    two_128.to_string(),
+    "340282366920938463463374607431768211456"
+  );
+  assert_eq!(BigCount::default().to_string(), "0");
+
+  let counts = element_counts::<u128>(&template, &rules, 10).unwrap();
+  assert_eq!(show_counts(&counts), "B=1749 C=298 H=161 N=865");
+}
+
+#[test]
+fn test_partial_rules_14() {
+  let (template, rules) = parse("NNCB\n\nNN -> C\nCB -> H");
+  let counts = element_counts(&template, &rules, 1).unwrap();
+  assert_eq!(
+    counts,
+    HashMap::from([('N', 2), ('C', 2), ('H', 1), ('B', 1)])
+  );
+  assert_eq!(element_counts(&template, &rules, 5).unwrap(), counts);
+
+  let modular = element_counts_mod(&template, &rules, 5, 1_000_000_007);
+  assert_eq!(
+    modular,
+    HashMap::from([('N', 2), ('C', 2), ('H', 1), ('B', 1)])
+  );
+}
+
+#[test]
+fn test_validate_rules_14() {
+  assert_eq!(
+    validate_rules(vec![
+      (('N', 'N'), 'C'),
+      (('C', 'B'), 'H'),
+      (('N', 'N'), 'C')
+    ]),
+    Err(RuleError::Duplicate(('N', 'N'), 'C'))
+  );
+  assert_eq!(
+    validate_rules(vec![(('N', 'N'), 'C'), (('N', 'N'), 'B')]),
+    Err(RuleError::Conflict(('N', 'N'), 'C', 'B'))
+  );
+  assert_eq!(
+    RuleError::Conflict(('N', 'N'), 'C', 'B').to_string(),
+    "pair NN inserts both C and B"
+  );
+}
+
+#[test]
+fn test_large_modulus_14() {
+  // the largest prime below 2^64, where adding raw products would overflow u128
+  const P: u64 = 18_446_744_073_709_551_557;
+  let (template, rules) = parse(EXAMPLE_INPUT);
+
+  for steps in [40, 64, 100] {
+    let exact = element_counts::<u128>(&template, &rules, steps).unwrap();
+    let modular = element_counts_mod(&template, &rules, steps as u64, P);
+    for (c, n) in exact {
+      assert_eq!(modular[&c] as u128, n % P as u128);
+    }
+  }
+
+  let counts = element_counts_mod(&template, &rules, 10, 1);
+  assert!(counts.values().all(|&n| n == 0));
+}
+
+#[test]
+#[should_panic(expected = "modulus must be positive")]
+fn test_zero_modulus_14() {
+  let (template, rules) = parse(EXAMPLE_INPUT);
+  element_counts_mod(&template, &rules, 10, 0);
+}
+
+#[test]
+fn test_element_counts_mod_14() {
+  const P: u64 = 1_000_000_007;
+  let (template, rules) = parse(EXAMPLE_INPUT);
+
+  for steps in [0, 1, 10, 40, 100] {
+    let exact = element_counts::<u128>(&template, &rules, steps).unwrap();
+    let modular = element_counts_mod(&template, &rules, steps as u64, P);
+    for (c, n) in exact {
+      assert_eq!(modular[&c] as u128, n % P as u128);
+    }
+  }
+
+  // the polymer is (len - 1) * 2^steps + 1 long
+  let steps: u64 = 1_000_000_000_000;
+  let counts = element_counts_mod(&template, &rules, steps, P);
+  let mut len = (template.len() as u64 - 1) % P;
+  let (mut base, mut n) = (2, steps);
+  while n > 0 {
+    if n & 1 == 1 {
+      len = len * base % P;
+    }
+    base = base * base % P;
+    n >>= 1;
+  }
+  assert_eq!(counts.values().sum::<u64>() % P, (len + 1) % P);
+}
+
 #[cfg(test)]
 const EXAMPLE_INPUT: &str = "\
 NNCB
@@ -8,7 +8,7 @@ use std::cmp::Reverse;
 type TParsed = Vec<TParsedSub>;
 type TParsedSub = Vec<usize>;
 
-pub fn day<'a>(input: String) -> (usize, usize) {
+pub fn day(input: String) -> (usize, usize) {
   let parsed_input = parse(&input);
   let p1 = part_1(&parsed_input);
   let p2 = part_2(&parsed_input);
@@ -97,18 +97,18 @@ fn part_2(input: &TParsed) -> usize {
   panic!("Unable to find destination!")
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, parse_line))(i)
 }
 
-fn parse_line<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_line(i: &str) -> IResult<&str, TParsedSub> {
   many1(map_res(take(1u8), |s: &str| s.parse()))(i)
 }
 
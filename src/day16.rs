@@ -1,14 +1,9 @@
 use hex::FromHex;
-use nom::bits::complete::{tag, take};
-use nom::branch::alt;
-use nom::multi::count;
-use nom::multi::many0;
-use nom::sequence::pair;
-use nom::sequence::preceded;
-use nom::IResult;
+use nom::bits::complete::take;
+use std::fmt;
 
 type THeader = (usize, usize);
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum Packet {
   Lit(THeader, usize),
   Op(THeader, Vec<Packet>),
@@ -21,36 +16,107 @@ impl Packet {
       Packet::Op((v, _), sub) => sub.iter().fold(*v, |r, x| r + x.get_v_sum()),
     }
   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Instr {
+  Push(usize),
+  Sum(usize),
+  Product(usize),
+  Min(usize),
+  Max(usize),
+  Gt,
+  Lt,
+  Eq,
+}
 
-  fn value(&self) -> usize {
+impl fmt::Display for Instr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
-      Packet::Lit((_, _), value) => *value,
-      Packet::Op((_, t), sub) => match t {
-        0 => sub.iter().fold(0usize, |r, x| r + x.value()),
-        1 => sub.iter().fold(1usize, |r, x| r * x.value()),
-        2 => sub.iter().map(|x| x.value()).min().unwrap(),
-        3 => sub.iter().map(|x| x.value()).max().unwrap(),
-        c => {
-          let l = sub[0].value();
-          let r = sub[1].value();
-          match c {
-            5 => (l > r) as usize * 1,
-            6 => (l < r) as usize * 1,
-            7 => (l == r) as usize * 1,
-            _ => panic!("uncovered"),
-          }
-        }
-      },
+      Instr::Push(v) => write!(f, "push {}", v),
+      Instr::Sum(n) => write!(f, "sum {}", n),
+      Instr::Product(n) => write!(f, "product {}", n),
+      Instr::Min(n) => write!(f, "min {}", n),
+      Instr::Max(n) => write!(f, "max {}", n),
+      Instr::Gt => write!(f, "gt"),
+      Instr::Lt => write!(f, "lt"),
+      Instr::Eq => write!(f, "eq"),
+    }
+  }
+}
+
+// post-order walk with an explicit stack so deep trees don't recurse
+fn compile(packet: &Packet) -> Vec<Instr> {
+  let mut code = Vec::new();
+  let mut todo = vec![(packet, false)];
+
+  while let Some((p, visited)) = todo.pop() {
+    match p {
+      Packet::Lit(_, v) => code.push(Instr::Push(*v)),
+      Packet::Op(_, sub) if !visited => {
+        todo.push((p, true));
+        todo.extend(sub.iter().rev().map(|x| (x, false)));
+      }
+      Packet::Op((_, t), sub) => code.push(match t {
+        0 => Instr::Sum(sub.len()),
+        1 => Instr::Product(sub.len()),
+        2 => Instr::Min(sub.len()),
+        3 => Instr::Max(sub.len()),
+        5 => Instr::Gt,
+        6 => Instr::Lt,
+        7 => Instr::Eq,
+        _ => panic!("uncovered"),
+      }),
     }
   }
+
+  code
+}
+
+fn run(code: &[Instr]) -> usize {
+  let mut stack: Vec<usize> = Vec::new();
+
+  for instr in code {
+    let v = match *instr {
+      Instr::Push(v) => v,
+      Instr::Sum(n) => stack.drain(stack.len() - n..).sum(),
+      Instr::Product(n) => stack.drain(stack.len() - n..).product(),
+      Instr::Min(n) => stack.drain(stack.len() - n..).min().unwrap(),
+      Instr::Max(n) => stack.drain(stack.len() - n..).max().unwrap(),
+      cmp => {
+        let r = stack.pop().unwrap();
+        let l = stack.pop().unwrap();
+        match cmp {
+          Instr::Gt => (l > r) as usize,
+          Instr::Lt => (l < r) as usize,
+          _ => (l == r) as usize,
+        }
+      }
+    };
+    stack.push(v);
+  }
+
+  stack.pop().expect("empty program")
+}
+
+fn disassemble(code: &[Instr]) -> String {
+  code
+    .iter()
+    .enumerate()
+    .map(|(i, instr)| format!("{:04} {}\n", i, instr))
+    .collect()
 }
 
 type TParsed = Packet;
 
 type PType<'a> = (&'a [u8], usize);
 
-pub fn day<'a>(input: String) -> (usize, usize) {
+pub fn day(input: String) -> (usize, usize) {
   let parsed_input = parse(&input);
+  if let Some(dir) = crate::main_util::output_dir() {
+    let asm = disassemble(&compile(&parsed_input));
+    std::fs::write(dir.join("day16.asm"), asm).expect("Unable to write day16.asm");
+  }
   let p1 = part_1(&parsed_input);
   let p2 = part_2(&parsed_input);
   (p1, p2)
@@ -61,86 +127,345 @@ fn part_1(input: &TParsed) -> usize {
 }
 
 fn part_2(input: &TParsed) -> usize {
-  input.value()
+  run(&compile(input))
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
-  match try_parse((&Vec::from_hex(input).expect("non"), 0)) {
-    Ok((_, v)) => v,
-    Err(e) => panic!("error parsing: {:?}", e),
+fn parse(input: &str) -> TParsed {
+  match parse_strict(input) {
+    Ok(v) => v,
+    Err(e) => panic!("error parsing: {}", e),
   }
 }
 
-fn try_parse<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
-  parse_packet(i)
-}
+// the original nom decoder and tree evaluator, kept to cross-check the strict decoder and the VM
+#[cfg(test)]
+mod lenient {
+  use super::{PType, Packet, TParsed};
+  use hex::FromHex;
+  use nom::bits::complete::{tag, take};
+  use nom::branch::alt;
+  use nom::multi::count;
+  use nom::multi::many0;
+  use nom::sequence::pair;
+  use nom::sequence::preceded;
+  use nom::IResult;
 
-fn parse_packet<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
-  alt((parse_lit, parse_op))(i)
-}
+  impl Packet {
+    pub(super) fn value(&self) -> usize {
+      match self {
+        Packet::Lit((_, _), value) => *value,
+        Packet::Op((_, t), sub) => match t {
+          0 => sub.iter().fold(0usize, |r, x| r + x.value()),
+          1 => sub.iter().fold(1usize, |r, x| r * x.value()),
+          2 => sub.iter().map(|x| x.value()).min().unwrap(),
+          3 => sub.iter().map(|x| x.value()).max().unwrap(),
+          c => {
+            let l = sub[0].value();
+            let r = sub[1].value();
+            match c {
+              5 => (l > r) as usize,
+              6 => (l < r) as usize,
+              7 => (l == r) as usize,
+              _ => panic!("uncovered"),
+            }
+          }
+        },
+      }
+    }
+  }
 
-fn parse_lit<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
-  let (i, v) = take(3usize)(i)?;
-  let (i, _) = tag(4, 3usize)(i)?;
+  pub(super) fn parse_lenient(input: &str) -> Option<TParsed> {
+    let bytes = Vec::from_hex(input).ok()?;
+    try_parse((&bytes, 0)).ok().map(|(_, v)| v)
+  }
 
-  let p_head = preceded(tag(1, 1usize), take(4usize));
-  let p_tail = preceded(tag(0, 1usize), take(4usize));
-  let (i, (l, r)): (_, (Vec<usize>, usize)) = pair(many0(p_head), p_tail)(i)?;
+  fn try_parse<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
+    parse_packet(i)
+  }
 
-  let mut t: usize = 0;
-  for n in l {
+  pub(super) fn parse_packet<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
+    alt((parse_lit, parse_op))(i)
+  }
+
+  fn parse_lit<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
+    let (i, v) = take(3usize)(i)?;
+    let (i, _) = tag(4, 3usize)(i)?;
+
+    let p_head = preceded(tag(1, 1usize), take(4usize));
+    let p_tail = preceded(tag(0, 1usize), take(4usize));
+    let (i, (l, r)): (_, (Vec<usize>, usize)) = pair(many0(p_head), p_tail)(i)?;
+
+    let mut t: usize = 0;
+    for n in l {
+      t <<= 4;
+      t |= n;
+    }
     t <<= 4;
-    t |= n;
+    t |= r;
+
+    Ok((i, Packet::Lit((v, 4), t)))
+  }
+
+  fn parse_op<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
+    let (i, v) = take(3usize)(i)?;
+    let (i, t) = take(3usize)(i)?;
+    let (i, sub_packets) = alt((parse_op_0, parse_op_1))(i)?;
+    Ok((i, Packet::Op((v, t), sub_packets)))
+  }
+
+  fn parse_op_0<'a>(i: PType<'a>) -> IResult<PType<'a>, Vec<Packet>> {
+    let (i, _) = tag(0, 1usize)(i)?;
+    let (i, l): (_, usize) = take(15usize)(i)?;
+
+    let take_bite = take(8usize);
+    let (i, mut new_bytes) = count(take_bite, l / 8)(i)?;
+    let rest = l % 8;
+
+    let (i, yeet): (_, u8) = take(rest)(i)?;
+
+    if rest > 0 {
+      new_bytes.push(yeet << (8 - rest));
+    }
+
+    let (_, p) = many0(parse_packet)((&new_bytes, 0)).expect("unable to parse subpackets");
+
+    Ok((i, p))
+  }
+
+  fn parse_op_1<'a>(i: PType<'a>) -> IResult<PType<'a>, Vec<Packet>> {
+    let (i, _) = tag(1, 1usize)(i)?;
+    let (i, l): (_, usize) = take(11usize)(i)?;
+    count(parse_packet, l)(i)
   }
-  t <<= 4;
-  t |= r;
+}
 
-  Ok((i, Packet::Lit((v, 4), t)))
+#[derive(Debug, PartialEq)]
+enum DecodeError {
+  InvalidHex {
+    offset: usize,
+    c: char,
+  },
+  OddLength {
+    bits: usize,
+  },
+  Truncated {
+    offset: usize,
+    needed: usize,
+    available: usize,
+  },
+  LiteralOverflow {
+    offset: usize,
+  },
+  LengthMismatch {
+    offset: usize,
+    expected: usize,
+    actual: usize,
+  },
+  LeftoverBits {
+    offset: usize,
+    count: usize,
+  },
+  NonZeroPadding {
+    offset: usize,
+  },
+  Arity {
+    offset: usize,
+    type_id: usize,
+    count: usize,
+  },
 }
 
-fn parse_op<'a>(i: PType<'a>) -> IResult<PType<'a>, Packet> {
-  let (i, v) = take(3usize)(i)?;
-  let (i, t) = take(3usize)(i)?;
-  let (i, sub_packets) = alt((parse_op_0, parse_op_1))(i)?;
-  Ok((i, Packet::Op((v, t), sub_packets)))
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DecodeError::InvalidHex { offset, c } => {
+        write!(f, "bit {}: invalid hex character {:?}", offset, c)
+      }
+      DecodeError::OddLength { bits } => {
+        write!(f, "bit {}: transmission ends on half a byte", bits)
+      }
+      DecodeError::Truncated {
+        offset,
+        needed,
+        available,
+      } => write!(
+        f,
+        "bit {}: needed {} bits but only {} remain",
+        offset, needed, available
+      ),
+      DecodeError::LiteralOverflow { offset } => {
+        write!(f, "bit {}: literal value does not fit in usize", offset)
+      }
+      DecodeError::LengthMismatch {
+        offset,
+        expected,
+        actual,
+      } => write!(
+        f,
+        "bit {}: sub-packets declared {} bits but used {}",
+        offset, expected, actual
+      ),
+      DecodeError::LeftoverBits { offset, count } => {
+        write!(f, "bit {}: {} leftover sub-packet bits", offset, count)
+      }
+      DecodeError::NonZeroPadding { offset } => {
+        write!(f, "bit {}: non-zero padding after outermost packet", offset)
+      }
+      DecodeError::Arity {
+        offset,
+        type_id,
+        count,
+      } => write!(
+        f,
+        "bit {}: operator type {} cannot take {} sub-packets",
+        offset, type_id, count
+      ),
+    }
+  }
 }
 
-fn parse_op_0<'a>(i: PType<'a>) -> IResult<PType<'a>, Vec<Packet>> {
-  let (i, _) = tag(0, 1usize)(i)?;
-  let (i, l): (_, usize) = take(15usize)(i)?;
+// smallest possible packet: header plus a single literal group
+const MIN_PACKET_BITS: usize = 11;
 
-  let take_bite = take(8usize);
-  let (i, mut new_bytes) = count(take_bite, l / 8)(i)?;
-  let rest = l % 8;
+fn parse_strict(input: &str) -> Result<Packet, DecodeError> {
+  let bytes = Vec::from_hex(input).map_err(|e| match e {
+    hex::FromHexError::InvalidHexCharacter { c, index } => DecodeError::InvalidHex {
+      offset: index * 4,
+      c,
+    },
+    _ => DecodeError::OddLength {
+      bits: input.len() * 4,
+    },
+  })?;
 
-  let (i, yeet): (_, u8) = take(rest)(i)?;
+  let (mut i, packet) = strict_packet(&bytes, (&bytes, 0))?;
 
-  if rest > 0 {
-    new_bytes.push(yeet << (8 - rest));
+  while bit_offset(&bytes, i) < bytes.len() * 8 {
+    let offset = bit_offset(&bytes, i);
+    let (rest, bit) = strict_take(&bytes, i, 1)?;
+    if bit != 0 {
+      return Err(DecodeError::NonZeroPadding { offset });
+    }
+    i = rest;
   }
 
-  let (_, p) = many0(parse_packet)((&new_bytes, 0)).expect("unable to parse subpackets");
+  Ok(packet)
+}
 
-  Ok((i, p))
+fn bit_offset(start: &[u8], (rest, bit): PType) -> usize {
+  (start.len() - rest.len()) * 8 + bit
 }
 
-fn parse_op_1<'a>(i: PType<'a>) -> IResult<PType<'a>, Vec<Packet>> {
-  let (i, _) = tag(1, 1usize)(i)?;
-  let (i, l): (_, usize) = take(11usize)(i)?;
-  count(parse_packet, l)(i)
+fn strict_take<'a>(
+  start: &[u8],
+  i: PType<'a>,
+  n: usize,
+) -> Result<(PType<'a>, usize), DecodeError> {
+  let offset = bit_offset(start, i);
+  let available = start.len() * 8 - offset;
+  if available < n {
+    return Err(DecodeError::Truncated {
+      offset,
+      needed: n,
+      available,
+    });
+  }
+  Ok(take::<_, usize, _, nom::error::Error<_>>(n)(i).expect("bits available"))
+}
+
+fn strict_packet<'a>(start: &[u8], i: PType<'a>) -> Result<(PType<'a>, Packet), DecodeError> {
+  let offset = bit_offset(start, i);
+  let (i, v) = strict_take(start, i, 3)?;
+  let (i, t) = strict_take(start, i, 3)?;
+
+  if t == 4 {
+    let lit_offset = bit_offset(start, i);
+    let mut i = i;
+    let mut value: usize = 0;
+    loop {
+      let (rest, more) = strict_take(start, i, 1)?;
+      let (rest, group) = strict_take(start, rest, 4)?;
+      if value.leading_zeros() < 4 {
+        return Err(DecodeError::LiteralOverflow { offset: lit_offset });
+      }
+      value = value << 4 | group;
+      i = rest;
+      if more == 0 {
+        return Ok((i, Packet::Lit((v, t), value)));
+      }
+    }
+  }
+
+  let (mut i, length_type) = strict_take(start, i, 1)?;
+  let mut sub_packets = Vec::new();
+
+  if length_type == 0 {
+    let (rest, l) = strict_take(start, i, 15)?;
+    i = rest;
+    let region = bit_offset(start, i);
+    let end = region + l;
+    let available = start.len() * 8 - region;
+    if available < l {
+      return Err(DecodeError::Truncated {
+        offset: region,
+        needed: l,
+        available,
+      });
+    }
+
+    while bit_offset(start, i) < end {
+      let offset = bit_offset(start, i);
+      if end - offset < MIN_PACKET_BITS {
+        return Err(DecodeError::LeftoverBits {
+          offset,
+          count: end - offset,
+        });
+      }
+      let (rest, p) = strict_packet(start, i)?;
+      if bit_offset(start, rest) > end {
+        return Err(DecodeError::LengthMismatch {
+          offset: region,
+          expected: l,
+          actual: bit_offset(start, rest) - region,
+        });
+      }
+      sub_packets.push(p);
+      i = rest;
+    }
+  } else {
+    let (rest, l) = strict_take(start, i, 11)?;
+    i = rest;
+    for _ in 0..l {
+      let (rest, p) = strict_packet(start, i)?;
+      sub_packets.push(p);
+      i = rest;
+    }
+  }
+
+  // min/max/sum/product need something to work on, comparisons need exactly two sides
+  let count = sub_packets.len();
+  if (t < 4 && count == 0) || (t > 4 && count != 2) {
+    return Err(DecodeError::Arity {
+      offset,
+      type_id: t,
+      count,
+    });
+  }
+
+  Ok((i, Packet::Op((v, t), sub_packets)))
 }
 
 #[test]
 fn yee_op_1() {
   let a = Vec::from_hex("EE00D40C823060").expect("yeet");
-  let input = parse_packet((&a, 0));
+  let input = lenient::parse_packet((&a, 0));
   println!("Out: {:?}", input);
 }
 
 #[test]
 fn yee_op_0() {
   let a = Vec::from_hex("38006F45291200").expect("yeet");
-  let input = parse_packet((&a, 0));
+  let input = lenient::parse_packet((&a, 0));
   println!("Out: {:?}", input);
 }
 
@@ -166,6 +491,121 @@ fn test_example_2_16() {
   }
 }
 
+#[test]
+fn test_vm_matches_tree_16() {
+  for e in EXAMPLE_INPUT2 {
+    let input = parse(e.0);
+    assert_eq!(run(&compile(&input)), input.value());
+    assert_eq!(run(&compile(&input)), e.1);
+  }
+}
+
+#[test]
+fn test_disassemble_16() {
+  let code = compile(&parse("9C0141080250320F1802104A08"));
+  assert_eq!(
+    disassemble(&code),
+    "\
+0000 push 1
+0001 push 3
+0002 sum 2
+0003 push 2
+0004 push 2
+0005 product 2
+0006 eq
+"
+  );
+}
+
+#[test]
+fn test_strict_matches_parse_16() {
+  for e in EXAMPLE_INPUT.iter().chain(EXAMPLE_INPUT2.iter()) {
+    assert_eq!(parse_strict(e.0).ok(), lenient::parse_lenient(e.0));
+  }
+  assert_eq!(parse_strict("D2FE28"), Ok(Packet::Lit((6, 4), 2021)));
+  assert_eq!(lenient::parse_lenient("D2FG28"), None);
+}
+
+#[test]
+fn test_strict_errors_16() {
+  assert_eq!(
+    parse_strict("D2FG28"),
+    Err(DecodeError::InvalidHex { offset: 12, c: 'G' })
+  );
+  assert_eq!(
+    parse_strict("D2FE2"),
+    Err(DecodeError::OddLength { bits: 20 })
+  );
+  assert_eq!(
+    parse_strict("38"),
+    Err(DecodeError::Truncated {
+      offset: 7,
+      needed: 15,
+      available: 1
+    })
+  );
+  assert_eq!(
+    parse_strict("D2FE29"),
+    Err(DecodeError::NonZeroPadding { offset: 23 })
+  );
+  assert_eq!(
+    parse_strict("3800304884"),
+    Err(DecodeError::LengthMismatch {
+      offset: 22,
+      expected: 12,
+      actual: 16
+    })
+  );
+  assert_eq!(
+    parse_strict("3800384080"),
+    Err(DecodeError::LeftoverBits {
+      offset: 33,
+      count: 3
+    })
+  );
+  assert_eq!(
+    parse_strict("13FFFFFFFFFFFFFFFFFFFC00"),
+    Err(DecodeError::LiteralOverflow { offset: 6 })
+  );
+}
+
+#[test]
+fn test_strict_arity_16() {
+  assert_eq!(
+    parse_strict("0A0000"),
+    Err(DecodeError::Arity {
+      offset: 0,
+      type_id: 2,
+      count: 0
+    })
+  );
+  assert_eq!(
+    parse_strict("16004408"),
+    Err(DecodeError::Arity {
+      offset: 0,
+      type_id: 5,
+      count: 1
+    })
+  );
+  assert_eq!(
+    parse_strict("1E00C408811020"),
+    Err(DecodeError::Arity {
+      offset: 0,
+      type_id: 7,
+      count: 3
+    })
+  );
+  assert_eq!(
+    DecodeError::Arity {
+      offset: 0,
+      type_id: 5,
+      count: 1
+    }
+    .to_string(),
+    "bit 0: operator type 5 cannot take 1 sub-packets"
+  );
+}
+
 #[cfg(test)]
 const EXAMPLE_INPUT: [(&str, usize); 4] = [
   ("8A004A801A8002F478", 16),
@@ -1,17 +1,20 @@
-use itertools::Itertools;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::digit1;
 use nom::character::complete::newline;
-use nom::sequence::delimited;
+use nom::sequence::preceded;
 use nom::sequence::separated_pair;
-use nom::{combinator::*, multi::separated_list1, IResult};
+use nom::sequence::terminated;
+use nom::{combinator::*, multi::separated_list1, Finish, IResult};
 use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::str::FromStr;
 
 type TParsed = Vec<TParsedSub>;
 type TParsedSub = Tree;
 
-pub fn day<'a>(input: String) -> (usize, usize) {
+pub fn day(input: String) -> (usize, usize) {
   let parsed_input = parse(&input);
   let p1 = part_1(&parsed_input);
   let p2 = part_2(&parsed_input);
@@ -19,30 +22,93 @@ pub fn day<'a>(input: String) -> (usize, usize) {
 }
 
 fn part_1(input: &TParsed) -> usize {
-  let mut res = input[0].to_owned();
+  input.iter().sum::<Tree>().magnitude()
+}
+
+fn part_2(input: &TParsed) -> usize {
+  let input: Vec<Flat> = input.iter().map(Flat::from).collect();
+  max_pair(&input).0
+}
+
+// best magnitude over all ordered pairs, with the (left, right) indices that produced it
+fn max_pair(input: &[Flat]) -> (usize, (usize, usize)) {
+  let threads = std::thread::available_parallelism()
+    .map(|n| n.get())
+    .unwrap_or(1)
+    .min(input.len());
+
+  std::thread::scope(|scope| {
+    let workers: Vec<_> = (0..threads)
+      .map(|t| {
+        scope.spawn(move || {
+          let mut buf = Flat(Vec::new());
+          let mut best = None;
+          for l in (t..input.len()).step_by(threads) {
+            for r in (0..input.len()).filter(|&r| r != l) {
+              Flat::add_into(&input[l], &input[r], &mut buf);
+              let res = (buf.magnitude(), (l, r));
+              best = best_of(best, res);
+            }
+          }
+          best
+        })
+      })
+      .collect();
+
+    workers
+      .into_iter()
+      .filter_map(|w| w.join().expect("worker panicked"))
+      .fold(None, best_of)
+      .expect("need at least two snailfish numbers")
+  })
+}
 
-  for i in 1..input.len() {
-    res = Tree::add(res, input[i].to_owned());
+// ties go to the lowest indices so the answer doesn't depend on the thread count
+fn best_of(
+  acc: Option<(usize, (usize, usize))>,
+  res: (usize, (usize, usize)),
+) -> Option<(usize, (usize, usize))> {
+  match acc {
+    Some(a) if a.0 > res.0 || (a.0 == res.0 && a.1 < res.1) => Some(a),
+    _ => Some(res),
   }
+}
 
-  res.magnitude()
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+  Explode(String),
+  Split(String),
 }
 
-fn part_2(input: &TParsed) -> usize {
-  input
-    .into_iter()
-    .tuple_combinations()
-    .map(|(l, r)| {
-      std::cmp::max(
-        Tree::add(l.to_owned(), r.to_owned()).magnitude(),
-        Tree::add(r.to_owned(), l.to_owned()).magnitude(),
-      )
-    })
-    .max()
-    .unwrap()
+impl fmt::Display for Action {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Action::Explode(path) => write!(f, "explode at {}", path),
+      Action::Split(path) => write!(f, "split at {}", path),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum NotReduced {
+  TooDeep(String),
+  TooLarge(String, usize),
+}
+
+impl fmt::Display for NotReduced {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      NotReduced::TooDeep(path) => {
+        write!(f, "pair at {} is nested inside four pairs", path)
+      }
+      NotReduced::TooLarge(path, v) => {
+        write!(f, "regular number {} at {} is 10 or greater", v, path)
+      }
+    }
+  }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Tree {
   Leaf(usize),
   Node { l: Box<Tree>, r: Box<Tree> },
@@ -50,6 +116,7 @@ enum Tree {
 
 impl Tree {
   fn add(l: Tree, r: Tree) -> Tree {
+    debug_assert!(l.is_reduced().is_ok() && r.is_reduced().is_ok());
     let mut tree = Tree::Node {
       l: Box::new(l),
       r: Box::new(r),
@@ -64,10 +131,7 @@ impl Tree {
   }
 
   fn is_node(&self) -> bool {
-    match self {
-      Tree::Node { l: _, r: _ } => true,
-      _ => false,
-    }
+    matches!(self, Tree::Node { l: _, r: _ })
   }
 
   fn is_value_node(&self) -> bool {
@@ -117,7 +181,7 @@ impl Tree {
         let half = v / 2;
         let l = Box::new(Tree::Leaf(half));
         let r = Box::new(Tree::Leaf(v - half));
-        Tree::Node { l: l, r: r }
+        Tree::Node { l, r }
       }
       _ => panic!("Can't split Node"),
     }
@@ -201,6 +265,144 @@ impl Tree {
     }
   }
 
+  // same reduction as `add`, but one action at a time so every step can be recorded
+  #[cfg(test)]
+  fn add_traced(l: Tree, r: Tree) -> (Tree, Vec<(Action, String)>) {
+    let mut tree = Tree::Node {
+      l: Box::new(l),
+      r: Box::new(r),
+    };
+    let mut trace = Vec::new();
+
+    while let Some(action) = tree.reduce_step() {
+      trace.push((action, tree.to_string()));
+    }
+
+    (tree, trace)
+  }
+
+  // normalizes any tree, however deep or large, into reduced form
+  fn reduce(mut self) -> Tree {
+    while self.reduce_step().is_some() {}
+    self
+  }
+
+  fn is_reduced(&self) -> Result<(), NotReduced> {
+    fn too_deep(t: &Tree, depth: u8, path: &mut String) -> bool {
+      match t {
+        Tree::Leaf(_) => false,
+        Tree::Node { .. } if depth >= 4 => true,
+        Tree::Node { l, r } => {
+          for (side, c) in [('L', l), ('R', r)] {
+            path.push(side);
+            if too_deep(c, depth + 1, path) {
+              return true;
+            }
+            path.pop();
+          }
+          false
+        }
+      }
+    }
+
+    fn too_large(t: &Tree, path: &mut String) -> Option<usize> {
+      match t {
+        Tree::Leaf(v) if *v > 9 => Some(*v),
+        Tree::Leaf(_) => None,
+        Tree::Node { l, r } => {
+          for (side, c) in [('L', l), ('R', r)] {
+            path.push(side);
+            if let Some(v) = too_large(c, path) {
+              return Some(v);
+            }
+            path.pop();
+          }
+          None
+        }
+      }
+    }
+
+    let mut path = String::new();
+    if too_deep(self, 0, &mut path) {
+      return Err(NotReduced::TooDeep(path));
+    }
+    if let Some(v) = too_large(self, &mut path) {
+      return Err(NotReduced::TooLarge(path, v));
+    }
+    Ok(())
+  }
+
+  fn reduce_step(&mut self) -> Option<Action> {
+    let mut path = String::new();
+    if self.explode_first(0, &mut path).is_some() {
+      return Some(Action::Explode(path));
+    }
+    if self.split_first(&mut path) {
+      return Some(Action::Split(path));
+    }
+    None
+  }
+
+  fn explode_first(&mut self, depth: u8, path: &mut String) -> Option<(usize, usize)> {
+    if depth >= 4 && self.is_value_node() {
+      let res = self.values();
+      *self = Tree::Leaf(0);
+      return Some(res);
+    }
+
+    let (l, r) = match self {
+      Tree::Leaf(_) => return None,
+      Tree::Node {
+        ref mut l,
+        ref mut r,
+      } => (l, r),
+    };
+
+    path.push('L');
+    if let Some((cl, cr)) = l.explode_first(depth + 1, path) {
+      r.send_left(cr);
+      return Some((cl, 0));
+    }
+    path.pop();
+
+    path.push('R');
+    if let Some((cl, cr)) = r.explode_first(depth + 1, path) {
+      l.send_right(cl);
+      return Some((0, cr));
+    }
+    path.pop();
+
+    None
+  }
+
+  fn split_first(&mut self, path: &mut String) -> bool {
+    match self {
+      Tree::Leaf(v) if *v > 9 => {
+        *self = self.split();
+        true
+      }
+      Tree::Leaf(_) => false,
+      Tree::Node {
+        ref mut l,
+        ref mut r,
+      } => {
+        path.push('L');
+        if l.split_first(path) {
+          return true;
+        }
+        path.pop();
+
+        path.push('R');
+        if r.split_first(path) {
+          return true;
+        }
+        path.pop();
+
+        false
+      }
+    }
+  }
+
   fn height(&self) -> usize {
     match self {
       Tree::Leaf(_) => 0,
@@ -208,16 +410,18 @@ impl Tree {
     }
   }
 
-  fn parse_either<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+  fn parse_either(i: &str) -> IResult<&str, TParsedSub> {
     alt((Tree::parse_node, Tree::parse_leaf))(i)
   }
 
-  fn parse_node<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+  fn parse_node(i: &str) -> IResult<&str, TParsedSub> {
     map(
-      delimited(
+      preceded(
         tag("["),
-        separated_pair(Tree::parse_either, tag(","), Tree::parse_either),
-        tag("]"),
+        cut(terminated(
+          separated_pair(Tree::parse_either, tag(","), Tree::parse_either),
+          tag("]"),
+        )),
       ),
       |(l, r)| Tree::Node {
         l: Box::new(l),
@@ -226,8 +430,8 @@ impl Tree {
     )(i)
   }
 
-  fn parse_leaf<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
-    map(get_dig, |d| Tree::Leaf(d))(i)
+  fn parse_leaf(i: &str) -> IResult<&str, TParsedSub> {
+    map(get_dig, Tree::Leaf)(i)
   }
 }
 
@@ -240,18 +444,214 @@ impl fmt::Display for Tree {
   }
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+#[derive(Debug, PartialEq)]
+struct ParseTreeError {
+  offset: usize,
+}
+
+impl fmt::Display for ParseTreeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid snailfish number at offset {}", self.offset)
+  }
+}
+
+// parsed numbers come out reduced, so they can be added straight away
+impl FromStr for Tree {
+  type Err = ParseTreeError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Tree::parse_unreduced(s).map(Tree::reduce)
+  }
+}
+
+impl Tree {
+  fn parse_unreduced(s: &str) -> Result<Tree, ParseTreeError> {
+    all_consuming(Tree::parse_either)(s)
+      .finish()
+      .map(|(_, t)| t)
+      .map_err(|e| ParseTreeError {
+        offset: s.len() - e.input.len(),
+      })
+  }
+}
+
+impl Add for Tree {
+  type Output = Tree;
+
+  fn add(self, rhs: Tree) -> Tree {
+    Tree::add(self, rhs)
+  }
+}
+
+impl AddAssign for Tree {
+  fn add_assign(&mut self, rhs: Tree) {
+    let l = std::mem::replace(self, Tree::Leaf(0));
+    *self = Tree::add(l, rhs);
+  }
+}
+
+impl Sum for Tree {
+  fn sum<I: Iterator<Item = Tree>>(iter: I) -> Self {
+    iter
+      .reduce(Tree::add)
+      .expect("can't sum an empty list of snailfish numbers")
+  }
+}
+
+impl<'a> Sum<&'a Tree> for Tree {
+  fn sum<I: Iterator<Item = &'a Tree>>(iter: I) -> Self {
+    iter.cloned().sum()
+  }
+}
+
+// leaves in left to right order, tagged with their nesting depth
+#[derive(Debug, Clone, PartialEq)]
+struct Flat(Vec<(u8, usize)>);
+
+impl Flat {
+  #[cfg(test)]
+  fn add(l: &Flat, r: &Flat) -> Flat {
+    let mut res = Flat(Vec::with_capacity(l.0.len() + r.0.len()));
+    Flat::add_into(l, r, &mut res);
+    res
+  }
+
+  fn add_into(l: &Flat, r: &Flat, out: &mut Flat) {
+    out.0.clear();
+    out
+      .0
+      .extend(l.0.iter().chain(r.0.iter()).map(|&(d, v)| (d + 1, v)));
+    out.reduce();
+  }
+
+  fn reduce(&mut self) {
+    if self.0.iter().any(|&(d, _)| d > 5) {
+      // leaves i and i + 1 need not be siblings here, so look up each pair before exploding it
+      while let Some(i) = self.first_deep_pair() {
+        self.explode(i);
+      }
+    } else {
+      // a sum of reduced numbers only nests pairs of leaves at depth 5, so one pass will do
+      let mut i = 0;
+      while i < self.0.len() {
+        if self.0[i].0 > 4 {
+          self.explode(i);
+        }
+        i += 1;
+      }
+    }
+
+    while let Some(i) = self.0.iter().position(|&(_, v)| v > 9) {
+      let (d, v) = self.0[i];
+      self.0[i] = (d + 1, v / 2);
+      self.0.insert(i + 1, (d + 1, v - v / 2));
+      if d + 1 > 4 {
+        self.explode(i);
+      }
+    }
+  }
+
+  // index of the left leaf of the leftmost pair of leaves nested inside four pairs
+  fn first_deep_pair(&self) -> Option<usize> {
+    // the same bracket matching as `fold`, remembering which entries are still plain leaves
+    let mut stack: Vec<(u8, bool)> = Vec::new();
+    for (i, &(d, _)) in self.0.iter().enumerate() {
+      let mut cur = (d, true);
+      while let Some(&(pd, leaf)) = stack.last() {
+        if pd != cur.0 {
+          break;
+        }
+        if leaf && cur.1 && d > 4 {
+          return Some(i - 1);
+        }
+        stack.pop();
+        cur = (pd - 1, false);
+      }
+      stack.push(cur);
+    }
+    None
+  }
+
+  fn explode(&mut self, i: usize) {
+    let (d, l) = self.0[i];
+    let (_, r) = self.0.remove(i + 1);
+    if i > 0 {
+      self.0[i - 1].1 += l;
+    }
+    if let Some(next) = self.0.get_mut(i + 1) {
+      next.1 += r;
+    }
+    self.0[i] = (d - 1, 0);
+  }
+
+  fn magnitude(&self) -> usize {
+    self.fold(|l, r| 3 * l + 2 * r)
+  }
+
+  // collapses sibling leaves bottom-up, the same way the brackets would close
+  fn fold<T, F>(&self, f: F) -> T
+  where
+    T: From<usize>,
+    F: Fn(T, T) -> T,
+  {
+    let mut stack: Vec<(u8, T)> = Vec::new();
+    for &(d, v) in &self.0 {
+      let mut cur = (d, T::from(v));
+      while stack.last().map(|(pd, _)| *pd) == Some(cur.0) {
+        let (pd, pv) = stack.pop().unwrap();
+        cur = (pd - 1, f(pv, cur.1));
+      }
+      stack.push(cur);
+    }
+    stack.pop().expect("empty number").1
+  }
+}
+
+impl From<&Tree> for Flat {
+  fn from(tree: &Tree) -> Self {
+    fn walk(t: &Tree, depth: u8, out: &mut Vec<(u8, usize)>) {
+      match t {
+        Tree::Leaf(v) => out.push((depth, *v)),
+        Tree::Node { l, r } => {
+          walk(l, depth + 1, out);
+          walk(r, depth + 1, out);
+        }
+      }
+    }
+
+    let mut res = Vec::new();
+    walk(tree, 0, &mut res);
+    Flat(res)
+  }
+}
+
+impl From<usize> for Tree {
+  fn from(v: usize) -> Self {
+    Tree::Leaf(v)
+  }
+}
+
+impl From<&Flat> for Tree {
+  fn from(flat: &Flat) -> Self {
+    flat.fold(|l, r| Tree::Node {
+      l: Box::new(l),
+      r: Box::new(r),
+    })
+  }
+}
+
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
-    Ok((_, v)) => v,
+    Ok((_, v)) => v.into_iter().map(Tree::reduce).collect(),
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(newline, Tree::parse_either))(i)
 }
 
-fn get_dig<'a, A>(i: &'a str) -> IResult<&'a str, A>
+fn get_dig<A>(i: &str) -> IResult<&str, A>
 where
   A: std::str::FromStr,
 {
@@ -278,6 +678,170 @@ fn test_example_2_18() {
   assert_eq!(part_2(&input), 3993)
 }
 
+#[test]
+fn test_flat_matches_tree_18() {
+  use itertools::Itertools;
+
+  let input = parse(EXAMPLE_INPUT);
+  for (l, r) in input.iter().tuple_combinations() {
+    let tree = Tree::add(l.to_owned(), r.to_owned());
+    let flat = Flat::add(&Flat::from(l), &Flat::from(r));
+    assert_eq!(Tree::from(&flat).to_string(), tree.to_string());
+    assert_eq!(flat.magnitude(), tree.magnitude());
+  }
+}
+
+#[test]
+fn test_max_pair_18() {
+  let input: Vec<Flat> = parse(EXAMPLE_INPUT).iter().map(Flat::from).collect();
+  assert_eq!(max_pair(&input), (3993, (8, 0)));
+}
+
+#[test]
+fn test_from_str_18() {
+  let tree: Tree = "[[1,2],[[3,4],5]]".parse().unwrap();
+  assert_eq!(tree.to_string(), "[[1,2],[[3,4],5]]");
+  assert_eq!(tree.magnitude(), 143);
+
+  assert_eq!("[1,2".parse::<Tree>(), Err(ParseTreeError { offset: 4 }));
+  assert_eq!("[1,x]".parse::<Tree>(), Err(ParseTreeError { offset: 3 }));
+  assert_eq!("[1,2]]".parse::<Tree>(), Err(ParseTreeError { offset: 5 }));
+
+  let large: Tree = "[[1,123],4]".parse().unwrap();
+  assert_eq!(large.is_reduced(), Ok(()));
+  assert_eq!(
+    large.to_string(),
+    "[[[[6,0],[7,7]],[[7,7],[7,7]]],[[[8,9],[8,9]],[[8,9],[8,9]]]]"
+  );
+  let sum = large.clone() + "[1,1]".parse().unwrap();
+  assert_eq!(
+    sum,
+    Tree::add(large, Tree::parse_unreduced("[1,1]").unwrap())
+  );
+}
+
+#[test]
+fn test_ops_18() {
+  let parse_tree = |s: &str| s.parse::<Tree>().unwrap();
+  let expected = parse_tree("[[[[1,1],[2,2]],[3,3]],[4,4]]");
+
+  let sum: Tree = ["[1,1]", "[2,2]", "[3,3]", "[4,4]"]
+    .iter()
+    .map(|s| parse_tree(s))
+    .sum();
+  assert_eq!(sum, expected);
+
+  let mut acc = parse_tree("[[[1,1],[2,2]],[3,3]]");
+  acc += parse_tree("[4,4]");
+  assert_eq!(acc, expected);
+
+  assert_eq!(
+    parse_tree("[[[[4,3],4],4],[7,[[8,4],9]]]") + parse_tree("[1,1]"),
+    parse_tree("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]")
+  );
+
+  let set: std::collections::HashSet<Tree> = [sum, acc].into_iter().collect();
+  assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn test_reduce_18() {
+  let parse_tree = |s: &str| Tree::parse_unreduced(s).unwrap();
+
+  let deep = parse_tree("[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]");
+  assert_eq!(
+    deep.is_reduced(),
+    Err(NotReduced::TooDeep("LLLL".to_owned()))
+  );
+  assert_eq!(
+    deep.reduce(),
+    parse_tree("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]")
+  );
+
+  let large = parse_tree("[[1,123],4]");
+  assert_eq!(
+    large.is_reduced(),
+    Err(NotReduced::TooLarge("LR".to_owned(), 123))
+  );
+  let reduced = large.reduce();
+  assert_eq!(reduced.is_reduced(), Ok(()));
+  let mut flat = Flat::from(&parse_tree("[[1,123],4]"));
+  flat.reduce();
+  assert_eq!(reduced, Tree::from(&flat));
+
+  let very_deep = parse_tree("[[[[[[[1,2],3],4],5],6],7],8]");
+  assert_eq!(very_deep.reduce().is_reduced(), Ok(()));
+
+  for s in [
+    "[[[[[[[1,2],3],4],5],6],7],8]",
+    "[[[[[1,[2,3]],4],5],6],7]",
+    "[1,[2,[3,[4,[5,[6,[7,[8,9]]]]]]]]",
+    "[[[[[[9,9],[9,9]],[[9,9],[9,9]]],1],2],[[[[[[3,4],5],6],7],8],9]]",
+  ] {
+    let mut flat = Flat::from(&parse_tree(s));
+    flat.reduce();
+    assert_eq!(Tree::from(&flat), parse_tree(s).reduce(), "{}", s);
+  }
+}
+
+#[test]
+fn test_trace_18() {
+  let l = parse("[[[[4,3],4],4],[7,[[8,4],9]]]").remove(0);
+  let r = parse("[1,1]").remove(0);
+  let expected = Tree::add(l.to_owned(), r.to_owned());
+  let (tree, trace) = Tree::add_traced(l, r);
+
+  let trace: Vec<_> = trace.iter().map(|(a, t)| format!("{}: {}", a, t)).collect();
+  assert_eq!(
+    trace,
+    [
+      "explode at LLLL: [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+      "explode at LRRL: [[[[0,7],4],[15,[0,13]]],[1,1]]",
+      "split at LRL: [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+      "split at LRRR: [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+      "explode at LRRR: [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+    ]
+  );
+  assert_eq!(tree.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_trace_matches_add_18() {
+  use itertools::Itertools;
+
+  let input = parse(EXAMPLE_INPUT);
+  for (l, r) in input.iter().tuple_combinations() {
+    let (tree, _) = Tree::add_traced(l.to_owned(), r.to_owned());
+    let expected = Tree::add(l.to_owned(), r.to_owned());
+    assert_eq!(tree.to_string(), expected.to_string());
+  }
+}
+
+#[test]
+#[ignore]
+fn bench_flat_vs_tree_18() {
+  use crate::main_util::time_duration;
+  use itertools::Itertools;
+
+  let input = parse(&crate::main_util::get_input(18, 2021));
+  let flats: Vec<Flat> = input.iter().map(Flat::from).collect();
+
+  let tree = time_duration(|| {
+    for (l, r) in input.iter().tuple_combinations() {
+      Tree::add(l.to_owned(), r.to_owned()).magnitude();
+      Tree::add(r.to_owned(), l.to_owned()).magnitude();
+    }
+  });
+  let flat = time_duration(|| {
+    for (l, r) in flats.iter().tuple_combinations() {
+      Flat::add(l, r).magnitude();
+      Flat::add(r, l).magnitude();
+    }
+  });
+
+  println!("Tree: {}ms, Flat: {}ms", tree.as_millis(), flat.as_millis());
+}
+
 #[cfg(test)]
 const EXAMPLE_INPUT: &str = "\
 [[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
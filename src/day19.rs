@@ -1,3 +1,4 @@
+use hashbrown::HashMap;
 use hashbrown::HashSet;
 use itertools::Itertools;
 use nom::bytes::complete::tag;
@@ -7,116 +8,367 @@ use nom::character::complete::newline;
 use nom::sequence::delimited;
 use nom::sequence::pair;
 use nom::{combinator::*, multi::separated_list1, IResult};
-use priority_queue::PriorityQueue;
-use std::iter;
-use vek::mat::Mat4;
-use vek::vec::Vec4;
+use std::fmt;
+use std::path::Path;
+use vek::mat::Mat3;
+use vek::vec::Vec3;
 
 type TParsed = Vec<TParsedSub>;
 type TParsedSub = Vec<TVec>;
-type TVec = Vec4<TN>;
-type IVec = Vec4<i64>;
-type TMat = Mat4<TN>;
-type TN = f64;
+type TVec = Vec3<TN>;
+type TMat = Mat3<TN>;
+type TN = i64;
+type TPrint = HashMap<TN, Vec<(usize, usize)>>;
 
-pub fn day<'a>(input: String) -> (usize, usize) {
+pub fn day(input: String) -> (usize, usize) {
   let parsed_input = parse(&input);
-  let (p1, sensor_locs) = part_1(&parsed_input);
-  let p2 = part_2(&sensor_locs);
+  let (p1, transforms) = part_1(&parsed_input);
+  if let Some(dir) = crate::main_util::output_dir() {
+    export_ply(&parsed_input, &transforms, &dir.join("day19.ply"))
+      .expect("Unable to write day19.ply");
+  }
+  let p2 = part_2(&sensor_locs(&transforms));
   (p1, p2)
 }
 
-fn part_1(input: &TParsed) -> (usize, Vec<IVec>) {
-  let mut space = HashSet::with_capacity(500);
-  space.extend(input[0].iter().map(|v| v.as_()));
+// maps scanner-local points into scanner 0's frame: rotate by `mats[rot]`, then offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transform {
+  rot: usize,
+  mat: TMat,
+  offset: TVec,
+}
+
+impl Transform {
+  fn identity() -> Transform {
+    let mats = get_mats();
+    let rot = mats
+      .iter()
+      .position(|m| *m == TMat::identity())
+      .expect("identity rotation");
+    Transform {
+      rot,
+      mat: mats[rot],
+      offset: TVec::zero(),
+    }
+  }
+
+  fn apply(&self, p: TVec) -> TVec {
+    self.mat * p + self.offset
+  }
+}
 
-  let mut find = PriorityQueue::<_, _>::from_iter((1..input.len()).zip(iter::repeat(u16::MAX)));
+fn sensor_locs(transforms: &[Transform]) -> Vec<TVec> {
+  transforms.iter().map(|t| t.offset).collect()
+}
 
-  let sensors: Vec<Vec<HashSet<IVec>>> = input
+fn beacons(input: &TParsed, transforms: &[Transform]) -> Vec<TVec> {
+  let mut res: Vec<TVec> = input
     .iter()
-    .map(|s| {
-      let mats = get_mats();
-      (0..24)
-        .map(|i| {
-          s.iter()
-            .map(move |o| (*o * mats[i]).round().as_())
-            .collect()
-        })
-        .collect()
-    })
+    .zip(transforms)
+    .flat_map(|(s, t)| s.iter().map(move |p| t.apply(*p)))
+    .collect::<HashSet<_>>()
+    .into_iter()
     .collect();
+  res.sort_unstable_by_key(|v| v.into_tuple());
+  res
+}
+
+// ascii PLY point cloud, beacons in grey and scanners in red
+fn to_ply(beacons: &[TVec], scanners: &[TVec]) -> String {
+  let mut res = format!(
+    "ply\nformat ascii 1.0\nelement vertex {}\n\
+     property int x\nproperty int y\nproperty int z\n\
+     property uchar red\nproperty uchar green\nproperty uchar blue\n\
+     end_header\n",
+    beacons.len() + scanners.len()
+  );
+
+  let colored = beacons
+    .iter()
+    .map(|v| (v, "200 200 200"))
+    .chain(scanners.iter().map(|v| (v, "255 0 0")));
+  for (v, color) in colored {
+    res += &format!("{} {} {} {}\n", v.x, v.y, v.z, color);
+  }
 
-  let mut sensor_locs: Vec<IVec> = vec![IVec::zero()];
+  res
+}
 
-  while let Some((input_i, old_prio)) = find.pop() {
-    let mut res = HashSet::with_capacity(15);
+fn export_ply(input: &TParsed, transforms: &[Transform], path: &Path) -> std::io::Result<()> {
+  let ply = to_ply(&beacons(input, transforms), &sensor_locs(transforms));
+  std::fs::write(path, ply)
+}
 
-    'outer: for s in &space {
-      for other in &sensors[input_i] {
-        for test_anchor in other {
-          let origin_o: IVec = *s - test_anchor;
+fn part_1(input: &TParsed) -> (usize, Vec<Transform>) {
+  locate(input, OVERLAP).unwrap_or_else(|e| panic!("{}", e))
+}
 
-          let beacons = other
-            .iter()
-            .map(|o| o + origin_o)
-            .filter(|o| !space.contains(o));
+const OVERLAP: usize = 12;
 
-          res.extend(beacons);
+#[derive(Debug, PartialEq)]
+enum LocateError {
+  // matching works on distances between beacon pairs, so fewer than two shared beacons can't work
+  OverlapTooSmall(usize),
+  Unplaced {
+    scanners: Vec<usize>,
+    overlap: usize,
+  },
+}
 
-          if other.len() - res.len() >= 12 {
-            sensor_locs.push(origin_o);
-            break 'outer;
-          }
+impl fmt::Display for LocateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LocateError::OverlapTooSmall(overlap) => write!(
+        f,
+        "scanners must share at least 2 beacons to be matched, not {}",
+        overlap
+      ),
+      LocateError::Unplaced { scanners, overlap } => write!(
+        f,
+        "no scanner overlapping in {} beacons could be found for scanners {:?}",
+        overlap, scanners
+      ),
+    }
+  }
+}
 
-          res.clear();
-        }
+// places every scanner relative to scanner 0, or reports the ones that can't be reached
+fn locate(input: &TParsed, overlap: usize) -> Result<(usize, Vec<Transform>), LocateError> {
+  if overlap < 2 {
+    return Err(LocateError::OverlapTooSmall(overlap));
+  }
+  let mats = get_mats();
+  let prints: Vec<TPrint> = input.iter().map(|s| fingerprint(s)).collect();
+
+  let mut transforms = vec![None; input.len()];
+  let mut global: Vec<Vec<TVec>> = vec![Vec::new(); input.len()];
+  transforms[0] = Some(Transform::identity());
+  global[0] = input[0].clone();
+
+  let mut todo = vec![0];
+  while let Some(known) = todo.pop() {
+    let known_set: HashSet<TVec> = global[known].iter().copied().collect();
+
+    for other in 0..input.len() {
+      if transforms[other].is_some()
+        || shared_distances(&prints[known], &prints[other]) < overlap * (overlap - 1) / 2
+      {
+        continue;
+      }
+
+      let found = align(
+        (&global[known], &known_set, &prints[known]),
+        (&input[other], &prints[other]),
+        &mats,
+        overlap,
+      );
+      if let Some(t) = found {
+        global[other] = input[other].iter().map(|p| t.apply(*p)).collect();
+        transforms[other] = Some(t);
+        todo.push(other);
       }
     }
-    if res.len() > 0 {
-      for r in res {
-        space.insert(r);
+  }
+
+  let scanners: Vec<usize> = (0..input.len())
+    .filter(|&i| transforms[i].is_none())
+    .collect();
+  if !scanners.is_empty() {
+    return Err(LocateError::Unplaced { scanners, overlap });
+  }
+
+  let space: HashSet<TVec> = global.iter().flatten().copied().collect();
+  Ok((space.len(), transforms.into_iter().flatten().collect()))
+}
+
+// squared distance between every pair of beacons, which doesn't change under rotation
+fn fingerprint(scanner: &[TVec]) -> TPrint {
+  let mut res = TPrint::new();
+  for (a, b) in (0..scanner.len()).tuple_combinations() {
+    let d = scanner[a] - scanner[b];
+    res.entry(d.dot(d)).or_default().push((a, b));
+  }
+  res
+}
+
+fn shared_distances(l: &TPrint, r: &TPrint) -> usize {
+  l.iter()
+    .filter_map(|(d, pairs)| r.get(d).map(|o| pairs.len().min(o.len())))
+    .sum()
+}
+
+// only rotations that map a pair of equally distant beacons onto each other are tested
+fn align(
+  (known, known_set, known_print): (&[TVec], &HashSet<TVec>, &TPrint),
+  (other, other_print): (&[TVec], &TPrint),
+  mats: &[TMat],
+  min_overlap: usize,
+) -> Option<Transform> {
+  for (dist, pairs) in known_print {
+    let other_pairs = match other_print.get(dist) {
+      Some(o) => o,
+      None => continue,
+    };
+
+    for (&(a, b), &(c, d)) in pairs.iter().cartesian_product(other_pairs) {
+      let want = known[b] - known[a];
+      for (rot, mat) in mats.iter().enumerate() {
+        for (c, d) in [(c, d), (d, c)] {
+          if *mat * (other[d] - other[c]) != want {
+            continue;
+          }
+
+          let t = Transform {
+            rot,
+            mat: *mat,
+            offset: known[a] - *mat * other[c],
+          };
+          let overlap = other
+            .iter()
+            .filter(|p| known_set.contains(&t.apply(**p)))
+            .count();
+          if overlap >= min_overlap {
+            return Some(t);
+          }
+        }
       }
-    } else {
-      find.push(input_i, old_prio - 1);
     }
   }
 
-  (space.len(), sensor_locs)
+  None
 }
 
-fn part_2(input: &Vec<IVec>) -> usize {
+fn part_2(input: &[TVec]) -> usize {
   input
     .iter()
     .tuple_combinations()
-    .map(|(l, r)| (l - r).iter().map(|v| v.abs() as usize).sum())
+    .map(|(l, r)| (l - r).iter().map(|v| v.unsigned_abs() as usize).sum())
     .max()
     .unwrap()
 }
 
-fn get_mats() -> [TMat; 24] {
-  fn get_mat(i: usize) -> TMat {
-    let yrot = ((i % 4) * 90) as f64;
-    let zrot = (if i < 16 { i / 4 } else { 0 } * 90) as f64;
-    let xrot = (if i >= 16 {
-      if i < 20 {
-        1
-      } else {
-        3
+// the 24 proper rotations: every signed permutation matrix with determinant 1
+fn get_mats() -> Vec<TMat> {
+  let mut res = Vec::with_capacity(24);
+
+  for (perm, perm_sign) in [
+    ([0, 1, 2], 1),
+    ([1, 2, 0], 1),
+    ([2, 0, 1], 1),
+    ([0, 2, 1], -1),
+    ([2, 1, 0], -1),
+    ([1, 0, 2], -1),
+  ] {
+    for signs in 0..8 {
+      let sign = |r: usize| if signs & (1 << r) == 0 { 1 } else { -1 };
+      if perm_sign * sign(0) * sign(1) * sign(2) != 1 {
+        continue;
+      }
+
+      let mut m = TMat::zero();
+      for r in 0..3 {
+        m[(r, perm[r])] = sign(r);
       }
-    } else {
-      0
-    } * 90) as f64;
+      res.push(m);
+    }
+  }
+
+  res
+}
+
+#[cfg(test)]
+struct Synthetic {
+  input: String,
+  beacons: usize,
+  scanners: Vec<Transform>,
+}
+
+// scanner 0 sits at the origin unrotated, every later scanner overlaps one placed before it;
+// None if some scanner can't find a spot that sees enough known beacons
+#[cfg(test)]
+fn generate(seed: u64, beacons: usize, scanners: usize, range: TN) -> Option<Synthetic> {
+  const ATTEMPTS: usize = 10_000;
+  if scanners > 1 && beacons < OVERLAP {
+    return None;
+  }
+
+  let mut state = seed.max(1);
+  let mut rand = |lo: TN, hi: TN| -> TN {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    lo + (state % (hi - lo + 1) as u64) as TN
+  };
+
+  let world = range * 2;
+  let cloud: Vec<TVec> = (0..beacons)
+    .map(|_| {
+      TVec::new(
+        rand(-world, world),
+        rand(-world, world),
+        rand(-world, world),
+      )
+    })
+    .collect();
+
+  let mats = get_mats();
+  let sees = |pos: TVec| -> HashSet<TVec> {
+    cloud
+      .iter()
+      .filter(|b| (**b - pos).iter().all(|v| v.abs() <= range))
+      .copied()
+      .collect()
+  };
 
-    TMat::rotation_x(xrot.to_radians())
-      * TMat::rotation_z(zrot.to_radians())
-      * TMat::rotation_y(yrot.to_radians())
+  let mut placed = vec![Transform::identity()];
+  let mut seen = vec![sees(TVec::zero())];
+  let mut attempts = 0;
+  while placed.len() < scanners {
+    attempts += 1;
+    if attempts > ATTEMPTS {
+      return None;
+    }
+    let from = placed[rand(0, placed.len() as TN - 1) as usize].offset;
+    let pos = from
+      + TVec::new(
+        rand(-range, range),
+        rand(-range, range),
+        rand(-range, range),
+      );
+    let view = sees(pos);
+    if seen
+      .iter()
+      .any(|s| s.intersection(&view).count() >= OVERLAP)
+    {
+      let rot = rand(0, 23) as usize;
+      placed.push(Transform {
+        rot,
+        mat: mats[rot],
+        offset: pos,
+      });
+      seen.push(view);
+      attempts = 0;
+    }
   }
 
-  let mut res = [TMat::zero(); 24];
-  for i in 0..24 {
-    res[i] = get_mat(i);
+  let mut input = String::new();
+  for (i, (t, view)) in placed.iter().zip(&seen).enumerate() {
+    if i > 0 {
+      input += "\n\n";
+    }
+    input += &format!("--- scanner {} ---", i);
+    for b in view {
+      let local = t.mat.transposed() * (*b - t.offset);
+      input += &format!("\n{},{},{}", local.x, local.y, local.z);
+    }
   }
-  res
+
+  Some(Synthetic {
+    input,
+    beacons: seen.iter().flatten().unique().count(),
+    scanners: placed,
+  })
 }
 
 #[test]
@@ -136,32 +388,136 @@ fn test_example_1_19() {
 #[test]
 fn test_example_2_19() {
   let input = parse(EXAMPLE_INPUT);
-  assert_eq!(part_2(&part_1(&input).1), 3621)
+  assert_eq!(part_2(&sensor_locs(&part_1(&input).1)), 3621)
+}
+
+#[test]
+fn test_transforms_19() {
+  let input = parse(EXAMPLE_INPUT);
+  let (_, transforms) = part_1(&input);
+
+  assert_eq!(
+    sensor_locs(&transforms),
+    [
+      TVec::zero(),
+      TVec::new(68, -1246, -43),
+      TVec::new(1105, -1205, 1229),
+      TVec::new(-92, -2380, -20),
+      TVec::new(-20, -1133, 1061),
+    ]
+  );
+  assert_eq!(transforms[0], Transform::identity());
+  assert_eq!(transforms[1].mat, get_mats()[transforms[1].rot]);
+  assert_eq!(
+    transforms[1].apply(TVec::new(686, 422, 578)),
+    TVec::new(-618, -824, -621)
+  );
+
+  assert_eq!(beacons(&input, &transforms).len(), 79);
+}
+
+#[test]
+fn test_export_ply_19() {
+  let input = parse(EXAMPLE_INPUT);
+  let path = std::env::temp_dir().join("day19_test.ply");
+  let (_, transforms) = part_1(&input);
+  export_ply(&input, &transforms, &path).unwrap();
+  let ply = std::fs::read_to_string(&path).unwrap();
+  std::fs::remove_file(&path).unwrap();
+
+  let lines: Vec<&str> = ply.lines().collect();
+  assert_eq!(lines[0], "ply");
+  assert_eq!(lines[2], "element vertex 84");
+  assert_eq!(lines[9], "end_header");
+  assert_eq!(lines.len(), 10 + 84);
+  assert_eq!(lines[10], "-892 524 684 200 200 200");
+  assert!(lines.contains(&"68 -1246 -43 255 0 0"));
+  assert_eq!(lines.iter().filter(|l| l.ends_with(" 255 0 0")).count(), 5);
+}
+
+#[test]
+fn test_synthetic_19() {
+  for seed in 1..=5 {
+    let synthetic = generate(seed, 600, 12, 1000).unwrap();
+    let (count, transforms) = part_1(&parse(&synthetic.input));
+    assert_eq!(count, synthetic.beacons);
+    assert_eq!(transforms, synthetic.scanners);
+  }
+
+  assert!(generate(1, 10, 3, 1000).is_none());
+  assert!(generate(1, 600, 1, 1000).is_some());
+}
+
+#[test]
+fn test_overlap_19() {
+  let input = parse(EXAMPLE_INPUT);
+  assert_eq!(locate(&input, OVERLAP).map(|(n, _)| n), Ok(79));
+  assert_eq!(
+    locate(&input, 13),
+    Err(LocateError::Unplaced {
+      scanners: vec![1, 2, 3, 4],
+      overlap: 13
+    })
+  );
+
+  let without_1: TParsed = [0, 2, 3, 4].iter().map(|&i| input[i].clone()).collect();
+  assert_eq!(
+    locate(&without_1, OVERLAP),
+    Err(LocateError::Unplaced {
+      scanners: vec![1, 2, 3],
+      overlap: OVERLAP
+    })
+  );
+
+  assert_eq!(locate(&input, 0), Err(LocateError::OverlapTooSmall(0)));
+  assert_eq!(locate(&input, 1), Err(LocateError::OverlapTooSmall(1)));
+  assert_eq!(locate(&input, 2).map(|(_, t)| t.len()), Ok(5));
+}
+
+#[test]
+fn test_fingerprint_19() {
+  let input = parse(EXAMPLE_INPUT);
+  let prints: Vec<TPrint> = input.iter().map(|s| fingerprint(s)).collect();
+
+  assert!(shared_distances(&prints[0], &prints[1]) >= 66);
+  assert!(shared_distances(&prints[1], &prints[4]) >= 66);
+  assert!(shared_distances(&prints[0], &prints[2]) < 66);
+}
+
+#[test]
+fn test_mats_19() {
+  let mats = get_mats();
+  assert_eq!(mats.len(), 24);
+  assert_eq!(mats.iter().unique().count(), 24);
+  for m in &mats {
+    assert_eq!(m.determinant(), 1);
+    assert_eq!(*m * m.transposed(), TMat::identity());
+  }
 }
 
-fn parse<'a>(input: &'a str) -> TParsed {
+fn parse(input: &str) -> TParsed {
   match try_parse(input) {
     Ok((_, v)) => v,
     Err(e) => panic!("error parsing: {:?}", e),
   }
 }
 
-fn try_parse<'a>(i: &'a str) -> IResult<&'a str, TParsed> {
+fn try_parse(i: &str) -> IResult<&str, TParsed> {
   all_consuming(separated_list1(pair(newline, newline), parse_scanner))(i)
 }
 
-fn parse_scanner<'a>(i: &'a str) -> IResult<&'a str, TParsedSub> {
+fn parse_scanner(i: &str) -> IResult<&str, TParsedSub> {
   let (i, _) = delimited(tag("--- scanner "), digit1, tag(" ---\n"))(i)?;
   separated_list1(newline, parse_vec3)(i)
 }
 
-fn parse_vec3<'a>(i: &'a str) -> IResult<&'a str, TVec> {
+fn parse_vec3(i: &str) -> IResult<&str, TVec> {
   map(separated_list1(tag(","), get_dig_n), |v| {
-    TVec::new(v[0], v[1], v[2], 0 as TN)
+    TVec::new(v[0], v[1], v[2])
   })(i)
 }
 
-fn get_dig_n<'a, A>(i: &'a str) -> IResult<&'a str, A>
+fn get_dig_n<A>(i: &str) -> IResult<&str, A>
 where
   A: std::str::FromStr,
 {
@@ -1,3 +1,5 @@
+#![allow(clippy::zero_prefixed_literal)]
+
 extern crate nom;
 
 pub use util::*;
@@ -1,3 +1,4 @@
 pub use paste::paste;
 
 pub mod main_util;
+pub mod ocr;
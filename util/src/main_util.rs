@@ -1,5 +1,5 @@
 use colored::Colorize;
-use std::{fs, io::Result, time};
+use std::{fs, io::Result, path::PathBuf, str::FromStr, time};
 
 #[macro_export]
 macro_rules! main {
@@ -83,6 +83,24 @@ where
     );
 }
 
+/// Directory named by `AOC_OUTPUT`, where days drop extra artifacts such as images or graphs.
+/// Returns `None` when the variable isn't set, so nothing is written by default.
+pub fn output_dir() -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os("AOC_OUTPUT")?);
+    fs::create_dir_all(&dir).expect("Unable to create output directory");
+    Some(dir)
+}
+
+/// Value of the environment variable `name`, for days that take extra settings.
+/// Panics if it is set but doesn't parse, rather than silently falling back.
+pub fn env_arg<T: FromStr>(name: &str) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+    match value.parse() {
+        Ok(v) => Some(v),
+        Err(_) => panic!("Unable to parse {}={}", name, value),
+    }
+}
+
 pub fn get_input(day: usize, year: usize) -> String {
     let file_path = format!("input/day{:02}", day);
     let file_content = fs::read_to_string(&file_path);
@@ -114,10 +132,10 @@ fn get_online_input(day: usize, year: usize) -> Result<String> {
 
     let resp = response.into_string()?;
     if resp.starts_with(TOO_EARLY) {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Day {} {} has not started yet", day, year),
-        ))
+        Err(std::io::Error::other(format!(
+            "Day {} {} has not started yet",
+            day, year
+        )))
     } else {
         Ok(resp)
     }
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+
+// The letters are drawn 4 dots wide and 6 high, with one empty column between them
+const LETTERS: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Reads the letters spelled by a set of lit `(x, y)` dots, using `?` for unknown shapes.
+///
+/// `origin` is the top left corner of the first glyph. It can't be taken from the dots
+/// themselves, since a glyph such as `I` leaves its first column empty.
+pub fn parse_letters<I>(dots: I, origin: (usize, usize)) -> String
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    let dots: HashSet<(usize, usize)> = dots.into_iter().collect();
+    let (x0, y0) = origin;
+    let x1 = match dots.iter().map(|d| d.0).max() {
+        Some(x1) if x1 >= x0 => x1,
+        _ => return String::new(),
+    };
+
+    let letters = (x1 - x0 + 1 + GLYPH_WIDTH) / (GLYPH_WIDTH + 1);
+    (0..letters)
+        .map(|i| {
+            let left = x0 + i * (GLYPH_WIDTH + 1);
+            let glyph: Vec<String> = (y0..y0 + GLYPH_HEIGHT)
+                .map(|y| {
+                    (left..left + GLYPH_WIDTH)
+                        .map(|x| if dots.contains(&(x, y)) { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+
+            LETTERS
+                .iter()
+                .find(|(_, shape)| shape.iter().eq(glyph.iter()))
+                .map_or('?', |(c, _)| *c)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn dots(drawing: &[&str], (dx, dy): (usize, usize)) -> Vec<(usize, usize)> {
+    drawing
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| (x + dx, y + dy))
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_letters() {
+    let drawing = [
+        "###...##..#..#.###..#..#.#....#..#.#...",
+        "#..#.#..#.#..#.#..#.#.#..#....#.#..#...",
+        "#..#.#....####.#..#.##...#....##...#...",
+        "###..#.##.#..#.###..#.#..#....#.#..#...",
+        "#....#..#.#..#.#.#..#.#..#....#.#..#...",
+        "#.....###.#..#.#..#.#..#.####.#..#.####",
+    ];
+    assert_eq!(parse_letters(dots(&drawing, (3, 7)), (3, 7)), "PGHRKLKL");
+    assert_eq!(parse_letters(Vec::new(), (0, 0)), "");
+}
+
+#[test]
+fn test_parse_letters_leading_i() {
+    // `I` leaves its first column empty, so the leftmost dot is not where the text starts
+    let drawing = [
+        ".###..##.",
+        "..#..#..#",
+        "..#..#..#",
+        "..#..####",
+        "..#..#..#",
+        ".###.#..#",
+    ];
+    assert_eq!(parse_letters(dots(&drawing, (0, 0)), (0, 0)), "IA");
+    assert_eq!(parse_letters(dots(&drawing, (2, 1)), (2, 1)), "IA");
+
+    let drawing = [
+        ".##...###",
+        "#..#...#.",
+        "#..#...#.",
+        "####...#.",
+        "#..#...#.",
+        "#..#..###",
+    ];
+    assert_eq!(parse_letters(dots(&drawing, (0, 0)), (0, 0)), "AI");
+}